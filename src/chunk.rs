@@ -0,0 +1,209 @@
+use std::marker::PhantomData;
+
+/// width and height of a chunk in pixels
+pub const CHUNK_SIZE: usize = 32;
+
+/// inclusive area of pixels inside a chunk that need updating
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl DirtyRect {
+    pub fn point(row: usize, col: usize) -> DirtyRect {
+        DirtyRect {
+            top: row,
+            bottom: row,
+            left: col,
+            right: col,
+        }
+    }
+
+    pub fn include(&mut self, row: usize, col: usize) {
+        self.top = self.top.min(row);
+        self.bottom = self.bottom.max(row);
+        self.left = self.left.min(col);
+        self.right = self.right.max(col);
+    }
+
+    /// the part of this rect that is also inside `other`
+    pub fn clip(self, other: DirtyRect) -> Option<DirtyRect> {
+        let clipped = DirtyRect {
+            top: self.top.max(other.top),
+            bottom: self.bottom.min(other.bottom),
+            left: self.left.max(other.left),
+            right: self.right.min(other.right),
+        };
+
+        if clipped.top > clipped.bottom || clipped.left > clipped.right {
+            None
+        } else {
+            Some(clipped)
+        }
+    }
+}
+
+/// a chunk is asleep while both rects are `None`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chunk {
+    /// what gets swept this tick
+    pub current: Option<DirtyRect>,
+    /// what was woken up during this tick, swept next tick
+    pub next: Option<DirtyRect>,
+}
+
+impl Chunk {
+    pub fn is_awake(&self) -> bool {
+        self.current.is_some() || self.next.is_some()
+    }
+}
+
+/// splits the map into `CHUNK_SIZE` squares so pixels that can't do anything
+/// don't cost anything. changing a pixel wakes it and its neighbours
+pub struct Chunks {
+    pub per_side: usize,
+    size: usize,
+    chunks: Vec<Chunk>,
+}
+
+impl Chunks {
+    /// every chunk starts fully awake so a fresh or loaded map settles
+    pub fn new(size: usize) -> Chunks {
+        let per_side = (size + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let mut chunks = Chunks {
+            per_side,
+            size,
+            chunks: vec![Chunk::default(); per_side * per_side],
+        };
+
+        for chunk_row in 0..per_side {
+            for chunk_col in 0..per_side {
+                let bounds = chunks.bounds(chunk_row, chunk_col);
+                chunks.chunks[chunk_row * per_side + chunk_col].next = Some(bounds);
+            }
+        }
+
+        chunks
+    }
+
+    pub fn get(&self, chunk_row: usize, chunk_col: usize) -> &Chunk {
+        &self.chunks[chunk_row * self.per_side + chunk_col]
+    }
+
+    /// pixels covered by a chunk
+    pub fn bounds(&self, chunk_row: usize, chunk_col: usize) -> DirtyRect {
+        DirtyRect {
+            top: chunk_row * CHUNK_SIZE,
+            bottom: ((chunk_row + 1) * CHUNK_SIZE).min(self.size) - 1,
+            left: chunk_col * CHUNK_SIZE,
+            right: ((chunk_col + 1) * CHUNK_SIZE).min(self.size) - 1,
+        }
+    }
+
+    /// which of the 4 passes a chunk runs in. no two chunks in the same pass
+    /// touch, so a pass can run on as many threads as it likes
+    pub fn phase(chunk_row: usize, chunk_col: usize) -> usize {
+        (chunk_row % 2) * 2 + chunk_col % 2
+    }
+
+    pub fn awake_count(&self) -> usize {
+        self.chunks.iter().filter(|c| c.is_awake()).count()
+    }
+
+    /// something changed at this pixel, so it and everything touching it
+    /// should be looked at next tick
+    pub fn wake(&mut self, row: usize, col: usize) {
+        for r in row.saturating_sub(1)..=(row + 1).min(self.size - 1) {
+            for c in col.saturating_sub(1)..=(col + 1).min(self.size - 1) {
+                self.mark(r, c);
+            }
+        }
+    }
+
+    fn mark(&mut self, row: usize, col: usize) {
+        let chunk = &mut self.chunks[(row / CHUNK_SIZE) * self.per_side + col / CHUNK_SIZE];
+        match &mut chunk.next {
+            Some(rect) => rect.include(row, col),
+            None => chunk.next = Some(DirtyRect::point(row, col)),
+        }
+    }
+
+    /// moves what was woken last tick into `current` for the chunks that are
+    /// about to be swept. chunks outside the range keep their work for later
+    pub fn begin_tick(&mut self, chunk_rows: (usize, usize), chunk_cols: (usize, usize)) {
+        for chunk_row in 0..self.per_side {
+            for chunk_col in 0..self.per_side {
+                let chunk = &mut self.chunks[chunk_row * self.per_side + chunk_col];
+
+                if chunk_row >= chunk_rows.0
+                    && chunk_row <= chunk_rows.1
+                    && chunk_col >= chunk_cols.0
+                    && chunk_col <= chunk_cols.1
+                {
+                    chunk.current = chunk.next.take();
+                } else {
+                    chunk.current = None;
+                }
+            }
+        }
+    }
+}
+
+/// how far a pixel rule may read or write away from the pixel it is updating.
+/// the furthest is fire setting off an explosive `IGNITE_REACH` away, which
+/// then blasts out its own radius from there
+pub const MAX_REACH: usize = 10;
+
+/// how far from a burning pixel fire can set something alight
+pub const IGNITE_REACH: usize = 2;
+
+// chunks that run at the same time have a whole chunk between them, so rules
+// can't reach each other's pixels as long as this holds
+const _: () = assert!(MAX_REACH * 2 <= CHUNK_SIZE);
+
+/// a grid that several threads can read and write at once.
+///
+/// nothing stops two threads from touching the same cell, that is up to the
+/// caller. `Map::update_state` only hands out cells through this while the
+/// chunks running are at least `CHUNK_SIZE` apart
+pub struct SharedGrid<'a, T> {
+    ptr: *mut T,
+    len: usize,
+    cols: usize,
+    _cells: PhantomData<&'a mut [T]>,
+}
+
+unsafe impl<T: Send> Send for SharedGrid<'_, T> {}
+unsafe impl<T: Send> Sync for SharedGrid<'_, T> {}
+
+impl<'a, T: Copy> SharedGrid<'a, T> {
+    pub fn new(cells: &'a mut [T], cols: usize) -> SharedGrid<'a, T> {
+        SharedGrid {
+            ptr: cells.as_mut_ptr(),
+            len: cells.len(),
+            cols,
+            _cells: PhantomData,
+        }
+    }
+
+    fn index(&self, (row, col): (usize, usize)) -> usize {
+        let index = row * self.cols + col;
+        assert!(col < self.cols && index < self.len, "({row}, {col}) is outside the grid");
+        index
+    }
+
+    /// # Safety
+    /// no other thread may be writing this cell
+    pub unsafe fn get(&self, pos: (usize, usize)) -> T {
+        self.ptr.add(self.index(pos)).read()
+    }
+
+    /// # Safety
+    /// no other thread may be reading or writing this cell
+    pub unsafe fn set(&self, pos: (usize, usize), value: T) {
+        self.ptr.add(self.index(pos)).write(value)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::season::Season;
+
+/// ticks in a day unless the settings say otherwise, about 5 minutes
+pub const DEFAULT_DAY_LENGTH: u32 = 18000;
+/// how much of the day dawn and dusk each take, as a fraction
+const TWILIGHT: f32 = 0.08;
+/// time of day the map starts at, early morning
+const FIRST_LIGHT: f32 = 0.3;
+
+/// colours of the sky as (r, g, b) at midday, at dawn/dusk and at night
+const DAY_SKY: (f32, f32, f32) = (0.4, 0.75, 1.0);
+const TWILIGHT_SKY: (f32, f32, f32) = (0.9, 0.5, 0.35);
+const NIGHT_SKY: (f32, f32, f32) = (0.03, 0.04, 0.1);
+
+/// the world's time of day
+pub struct Clock {
+    /// days since the map was made
+    pub day: u32,
+    /// how far through the day it is, 0.0 (midnight) - 1.0. the sun rises at
+    /// 0.25 and sets at 0.75
+    pub time: f32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock { day: 0, time: FIRST_LIGHT }
+    }
+}
+
+impl Clock {
+    /// moves the clock on by one tick of a day `day_length` ticks long
+    pub fn tick(&mut self, day_length: u32) {
+        self.time += 1.0 / day_length.max(1) as f32;
+        if self.time >= 1.0 {
+            self.time -= 1.0;
+            self.day += 1;
+        }
+    }
+
+    /// how much sun there is, 0.0 at night - 1.0 in the day. it ramps up and
+    /// down over dawn and dusk
+    pub fn daylight(&self) -> f32 {
+        let from_sunrise = (self.time - 0.25 + TWILIGHT / 2.0) / TWILIGHT;
+        let to_sunset = (0.75 + TWILIGHT / 2.0 - self.time) / TWILIGHT;
+        let light = from_sunrise.min(to_sunset).clamp(0.0, 1.0);
+        // smoothstep, so it eases in and out instead of stopping sharply
+        light * light * (3.0 - 2.0 * light)
+    }
+
+    /// colour of the sky behind the world as (r, g, b)
+    pub fn sky_color(&self) -> (f32, f32, f32) {
+        let daylight = self.daylight();
+        // the sky goes orange as the sun goes through the horizon
+        let twilight = 1.0 - (daylight * 2.0 - 1.0).abs();
+        let base = mix(NIGHT_SKY, DAY_SKY, daylight);
+        mix(base, TWILIGHT_SKY, twilight * 0.6)
+    }
+
+    pub fn season(&self) -> Season {
+        Season::of_day(self.day)
+    }
+
+    /// hours and minutes on a 24 hour clock
+    pub fn hours_minutes(&self) -> (u32, u32) {
+        let minutes = (self.time * 24.0 * 60.0) as u32;
+        (minutes / 60, minutes % 60)
+    }
+}
+
+fn mix(a: (f32, f32, f32), b: (f32, f32, f32), t: f32) -> (f32, f32, f32) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t, a.2 + (b.2 - a.2) * t)
+}
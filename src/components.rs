@@ -0,0 +1,398 @@
+use std::collections::{HashMap, VecDeque};
+
+use grid::Grid;
+
+use crate::map::Pixel;
+
+/// label of cells that aren't part of any component
+const NONE: u32 = u32::MAX;
+
+/// a body of one liquid or a pocket of air. the id stays the same for as long
+/// as the body exists, while it grows or shrinks, swallows smaller bodies or
+/// has small bits split off it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComponentId(u32);
+
+struct Component {
+    pixel: Pixel,
+    size: usize,
+}
+
+/// one of the searches `split` runs side by side
+#[derive(Default)]
+struct Search {
+    queue: VecDeque<(usize, usize)>,
+    cells: Vec<(usize, usize)>,
+    group: usize,
+}
+
+/// keeps every cell labelled with the body of liquid or pocket of air it is
+/// in. cells handed to `changed` are relabelled by `update`, which only looks
+/// at the area around them, so queries stay cheap on big maps
+pub struct Components {
+    labels: Grid<u32>,
+    components: HashMap<u32, Component>,
+    /// ids are never handed out twice, so a stale label can't point at a
+    /// different body
+    next_id: u32,
+    changed: Vec<(usize, usize)>,
+    /// which search last reached each cell, see `split`
+    seen: Grid<u32>,
+    search: u32,
+    queue: VecDeque<(usize, usize)>,
+    searches: Vec<Search>,
+}
+
+/// only air and liquids make up components, everything else is a wall
+/// between them
+fn joins(pixel: Pixel) -> bool {
+    pixel == Pixel::Air || pixel.fluid()
+}
+
+fn neighbours((row, col): (usize, usize), size: usize) -> impl Iterator<Item = (usize, usize)> {
+    [
+        (row.wrapping_sub(1), col),
+        (row + 1, col),
+        (row, col.wrapping_sub(1)),
+        (row, col + 1),
+    ]
+    .into_iter()
+    .filter(move |(row, col)| *row < size && *col < size)
+}
+
+impl Components {
+    pub fn new(size: usize) -> Components {
+        Components {
+            labels: Grid::from_vec(vec![NONE; size.pow(2)], size),
+            components: HashMap::default(),
+            next_id: 0,
+            changed: vec![],
+            seen: Grid::from_vec(vec![0; size.pow(2)], size),
+            search: 0,
+            queue: VecDeque::new(),
+            searches: vec![],
+        }
+    }
+
+    /// the component a cell is in, as of the last `update`
+    pub fn at(&self, pos: (usize, usize)) -> Option<ComponentId> {
+        match self.labels[pos] {
+            NONE => None,
+            label => Some(ComponentId(label)),
+        }
+    }
+
+    /// number of cells in a component, 0 once it is gone
+    pub fn size(&self, id: ComponentId) -> usize {
+        self.components.get(&id.0).map_or(0, |component| component.size)
+    }
+
+    /// what a component is made of
+    pub fn pixel(&self, id: ComponentId) -> Option<Pixel> {
+        self.components.get(&id.0).map(|component| component.pixel)
+    }
+
+    /// labels the whole map from scratch, for maps that were just made or
+    /// loaded
+    pub fn rebuild(&mut self, grid: &Grid<Pixel>) {
+        self.labels.iter_mut().for_each(|label| *label = NONE);
+        self.components.clear();
+        self.changed.clear();
+
+        for row in 0..grid.rows() {
+            for col in 0..grid.cols() {
+                let pixel = grid[(row, col)];
+                if joins(pixel) && self.labels[(row, col)] == NONE {
+                    let id = self.add_component(pixel, 0);
+                    let size = self.fill(grid, (row, col), pixel, NONE, id);
+                    self.components.get_mut(&id).unwrap().size = size;
+                }
+            }
+        }
+    }
+
+    /// marks a cell whose pixel might have changed, it is relabelled on the
+    /// next `update`
+    pub fn changed(&mut self, pos: (usize, usize)) {
+        self.changed.push(pos);
+    }
+
+    /// relabels everything around the cells passed to `changed`
+    pub fn update(&mut self, grid: &Grid<Pixel>) {
+        let mut changed = std::mem::take(&mut self.changed);
+        for pos in changed.drain(..) {
+            self.refresh(grid, pos);
+        }
+        self.changed = changed;
+    }
+
+    fn refresh(&mut self, grid: &Grid<Pixel>, pos: (usize, usize)) {
+        let pixel = grid[pos];
+        let label = self.labels[pos];
+
+        if let Some(component) = self.components.get(&label) {
+            if component.pixel == pixel {
+                return;
+            }
+            self.remove(grid, pos, label);
+        }
+        self.labels[pos] = NONE;
+
+        if joins(pixel) {
+            self.add(grid, pos, pixel);
+        }
+    }
+
+    /// whether `pos` is part of `label`. changes are applied one cell at a
+    /// time, so a cell counts as whatever it was labelled until it gets
+    /// refreshed. unlabelled cells only count as `NONE` if they are `pixel`
+    fn in_component(&self, grid: &Grid<Pixel>, pos: (usize, usize), pixel: Pixel, label: u32) -> bool {
+        self.labels[pos] == label && (label != NONE || grid[pos] == pixel)
+    }
+
+    fn add_component(&mut self, pixel: Pixel, size: usize) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.components.insert(id, Component { pixel, size });
+        id
+    }
+
+    /// puts a cell into the component next to it, joining up components it
+    /// touches. the biggest one keeps its id and the others are relabelled
+    fn add(&mut self, grid: &Grid<Pixel>, pos: (usize, usize), pixel: Pixel) {
+        let mut touching: Vec<((usize, usize), u32)> = vec![];
+        for next in neighbours(pos, grid.rows()) {
+            let label = self.labels[next];
+            if self.components.get(&label).is_some_and(|component| component.pixel == pixel)
+                && !touching.iter().any(|(_, other)| *other == label)
+            {
+                touching.push((next, label));
+            }
+        }
+
+        let Some(&(_, keep)) = touching.iter().max_by_key(|(_, label)| self.components[label].size) else {
+            self.labels[pos] = self.add_component(pixel, 1);
+            return;
+        };
+
+        self.labels[pos] = keep;
+        let mut size = self.components[&keep].size + 1;
+        for (start, label) in touching {
+            if label != keep {
+                size += self.fill(grid, start, pixel, label, keep);
+                self.components.remove(&label);
+            }
+        }
+        self.components.get_mut(&keep).unwrap().size = size;
+    }
+
+    /// takes a cell out of its component, splitting off whatever it was the
+    /// only link to
+    fn remove(&mut self, grid: &Grid<Pixel>, pos: (usize, usize), label: u32) {
+        self.labels[pos] = NONE;
+        let component = self.components.get_mut(&label).unwrap();
+        component.size -= 1;
+        if component.size == 0 {
+            self.components.remove(&label);
+            return;
+        }
+        let pixel = component.pixel;
+
+        let starts: Vec<(usize, usize)> = neighbours(pos, grid.rows())
+            .filter(|next| self.in_component(grid, *next, pixel, label))
+            .collect();
+        if starts.len() > 1 {
+            self.split(grid, label, pixel, &starts);
+        }
+    }
+
+    /// searches out from every cell next to a removed one at the same time.
+    /// searches that meet are still joined up, ones that run out on their
+    /// own are cut off and become new components. the last one still going
+    /// keeps the id, so the cost is the size of the smaller pieces rather
+    /// than the whole component
+    fn split(&mut self, grid: &Grid<Pixel>, label: u32, pixel: Pixel, starts: &[(usize, usize)]) {
+        let count = starts.len();
+        if self.search >= u32::MAX - count as u32 {
+            self.seen.iter_mut().for_each(|seen| *seen = 0);
+            self.search = 0;
+        }
+        let first = self.search + 1;
+        self.search += count as u32;
+
+        self.searches.resize_with(self.searches.len().max(count), Search::default);
+        for (i, start) in starts.iter().enumerate() {
+            let search = &mut self.searches[i];
+            search.queue.clear();
+            search.cells.clear();
+            search.queue.push_back(*start);
+            search.cells.push(*start);
+            search.group = i;
+            self.seen[*start] = first + i as u32;
+        }
+
+        // groups of searches that met each other and haven't run out yet
+        let mut groups = count;
+        while groups > 1 {
+            for i in 0..count {
+                let Some(pos) = self.searches[i].queue.pop_front() else {
+                    continue;
+                };
+                for next in neighbours(pos, grid.rows()) {
+                    if !self.in_component(grid, next, pixel, label) {
+                        continue;
+                    }
+                    let seen = self.seen[next];
+                    if seen >= first && seen < first + count as u32 {
+                        let (keep, merge) = (self.searches[i].group, self.searches[(seen - first) as usize].group);
+                        if keep != merge {
+                            self.searches[..count]
+                                .iter_mut()
+                                .filter(|search| search.group == merge)
+                                .for_each(|search| search.group = keep);
+                            groups -= 1;
+                        }
+                    } else {
+                        self.seen[next] = first + i as u32;
+                        self.searches[i].queue.push_back(next);
+                        self.searches[i].cells.push(next);
+                    }
+                }
+            }
+
+            for group in 0..count {
+                let mut searches = self.searches[..count].iter().filter(|search| search.group == group);
+                if groups <= 1 || searches.clone().next().is_none() || searches.any(|search| !search.queue.is_empty()) {
+                    continue;
+                }
+
+                let id = self.add_component(pixel, 0);
+                let mut size = 0;
+                for search in self.searches[..count].iter_mut().filter(|search| search.group == group) {
+                    for cell in &search.cells {
+                        self.labels[*cell] = id;
+                    }
+                    size += search.cells.len();
+                    search.group = usize::MAX;
+                }
+                self.components.get_mut(&id).unwrap().size = size;
+                self.components.get_mut(&label).unwrap().size -= size;
+                groups -= 1;
+            }
+        }
+    }
+
+    /// queue-linear flood fill: relabels the run of cells either side of each
+    /// queued cell in one go, then queues one cell per run above and below
+    /// it. returns how many cells it relabelled
+    fn fill(&mut self, grid: &Grid<Pixel>, start: (usize, usize), pixel: Pixel, from: u32, to: u32) -> usize {
+        let size = grid.rows();
+        let mut filled = 0;
+
+        self.queue.clear();
+        self.queue.push_back(start);
+        while let Some((row, col)) = self.queue.pop_front() {
+            if !self.in_component(grid, (row, col), pixel, from) {
+                continue;
+            }
+
+            let mut left = col;
+            while left > 0 && self.in_component(grid, (row, left - 1), pixel, from) {
+                left -= 1;
+            }
+            let mut right = col;
+            while right + 1 < size && self.in_component(grid, (row, right + 1), pixel, from) {
+                right += 1;
+            }
+
+            for col in left..=right {
+                self.labels[(row, col)] = to;
+            }
+            filled += right - left + 1;
+
+            for next_row in [row.wrapping_sub(1), row + 1] {
+                if next_row >= size {
+                    continue;
+                }
+                let mut in_run = false;
+                for col in left..=right {
+                    let open = self.in_component(grid, (next_row, col), pixel, from);
+                    if open && !in_run {
+                        self.queue.push_back((next_row, col));
+                    }
+                    in_run = open;
+                }
+            }
+        }
+
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 16;
+
+    /// stone everywhere but a line of water along row 8
+    fn channel() -> (Grid<Pixel>, Components) {
+        let mut grid = Grid::from_vec(vec![Pixel::Stone; SIZE.pow(2)], SIZE);
+        for col in 2..14 {
+            grid[(8, col)] = Pixel::Water;
+        }
+        let mut components = Components::new(SIZE);
+        components.rebuild(&grid);
+        (grid, components)
+    }
+
+    fn set(grid: &mut Grid<Pixel>, components: &mut Components, pos: (usize, usize), pixel: Pixel) {
+        grid[pos] = pixel;
+        components.changed(pos);
+        components.update(grid);
+    }
+
+    #[test]
+    fn split_keeps_ids_stable() {
+        let (mut grid, mut components) = channel();
+        let whole = components.at((8, 2)).unwrap();
+        assert_eq!(components.size(whole), 12);
+
+        // the bigger piece keeps the id, the smaller one gets a new one
+        set(&mut grid, &mut components, (8, 5), Pixel::Stone);
+        let left = components.at((8, 2)).unwrap();
+        assert_eq!(components.at((8, 13)), Some(whole));
+        assert_ne!(left, whole);
+        assert_eq!(components.size(left), 3);
+        assert_eq!(components.size(whole), 8);
+        assert!((2..5).all(|col| components.at((8, col)) == Some(left)));
+        assert!((6..14).all(|col| components.at((8, col)) == Some(whole)));
+
+        // cutting the bigger piece again leaves the first one alone
+        set(&mut grid, &mut components, (8, 11), Pixel::Stone);
+        assert_eq!(components.at((8, 2)), Some(left));
+        assert_eq!(components.at((8, 6)), Some(whole));
+        assert_eq!(components.size(whole), 5);
+        let right = components.at((8, 13)).unwrap();
+        assert!(right != left && right != whole);
+        assert_eq!(components.size(right), 2);
+
+        // changes elsewhere don't relabel anything
+        set(&mut grid, &mut components, (2, 2), Pixel::Dirt);
+        assert_eq!(components.at((8, 2)), Some(left));
+        assert_eq!(components.at((8, 6)), Some(whole));
+        assert_eq!(components.at((8, 13)), Some(right));
+    }
+
+    #[test]
+    fn joining_keeps_the_biggest_id() {
+        let (mut grid, mut components) = channel();
+        set(&mut grid, &mut components, (8, 5), Pixel::Stone);
+        let (left, whole) = (components.at((8, 2)).unwrap(), components.at((8, 13)).unwrap());
+
+        set(&mut grid, &mut components, (8, 5), Pixel::Water);
+        assert!((2..14).all(|col| components.at((8, col)) == Some(whole)));
+        assert_eq!(components.size(whole), 12);
+        assert_eq!(components.size(left), 0);
+    }
+}
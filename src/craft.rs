@@ -1,5 +1,4 @@
 use grid::Grid;
-use macroquad::shapes::draw_rectangle;
 
 use crate::map::Pixel;
 
@@ -34,6 +33,29 @@ pub fn craft(mut grid: Grid::<Pixel>) -> (bool, Grid::<bool>, Grid::<Pixel>) {
                     grid[(row,col +1)] = Pixel::Air;
                     grid[(row,col)] = Pixel::Lamp;
                 }
+                Pixel::Ice if row > 0 && col < grid.size().0 -1 && col > 0 && row < grid.size().0 -1 
+                && grid[(row-1,col)] == Pixel::Glass
+                && grid[(row+1,col)] == Pixel::Glass
+                && grid[(row,col+1)] == Pixel::Glass
+                && grid[(row,col-1)] == Pixel::Glass => {
+                    grid[(row-1,col)] = Pixel::Air;
+                    grid[(row+1,col)] = Pixel::Air;
+                    grid[(row,col -1)] = Pixel::Air;
+                    grid[(row,col +1)] = Pixel::Air;
+                    grid[(row,col)] = Pixel::Cryo;
+                }
+                Pixel::Copper if row > 0 && grid[(row-1,col)] == Pixel::Water => {
+                    grid[(row-1,col)] = Pixel::Air;
+                    grid[(row,col)] = Pixel::Acid;
+                }
+                Pixel::Coal if row > 0 && grid[(row-1,col)] == Pixel::Sand => {
+                    grid[(row-1,col)] = Pixel::Air;
+                    grid[(row,col)] = Pixel::Glass;
+                }
+                Pixel::Snow if row > 0 && grid[(row-1,col)] == Pixel::Snow => {
+                    grid[(row-1,col)] = Pixel::Air;
+                    grid[(row,col)] = Pixel::Ice;
+                }
 
                 Pixel::LiveWood => {
                     grid[(row,col)] = Pixel::Wood;
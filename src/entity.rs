@@ -2,10 +2,13 @@
 use std::clone;
 
 use grid::Grid;
-use egui_macroquad::macroquad::{math::{Rect, Vec2}, miniquad::FilterMode, texture::Texture2D, time::get_frame_time};
 use savefile_derive::Savefile;
 
-use crate::{map::{Map, Pixel}, physics::{self, CollisionDirection}};
+use glam::Vec2;
+
+use crate::map::{Map, Pixel};
+use crate::physics::{self, CollisionDirection};
+use crate::wind::Wind;
 #[derive(PartialEq, Debug, Clone)]
 // #[derive(PartialEq, Debug, Clone, Savefile)]
 
@@ -22,17 +25,18 @@ pub struct Entity {
     pub vy: f32,
     pub height: f32,
     pub width: f32,
-    pub texture: Texture2D,
+    /// which of the sprites for this entity type is used
+    pub variant: usize,
     pub entity_type: EntityType,
 }
 
 impl Entity {
-    pub fn new(entity_type: EntityType, x: f32, y: f32) -> Entity {
+    pub fn new(entity_type: EntityType, x: f32, y: f32, rng: &mut fastrand::Rng) -> Entity {
         let scale = entity_type.scale();
-        let texture = entity_type.texture();
-        texture.set_filter(FilterMode::Nearest);
-        let height = texture.height() * scale;
-        let width = texture.width() * scale;
+        let variant = rng.usize(0..entity_type.sprite_sizes().len());
+        let (sprite_width, sprite_height) = entity_type.sprite_sizes()[variant];
+        let height = sprite_height * scale;
+        let width = sprite_width * scale;
 
 
         return Entity {
@@ -42,14 +46,13 @@ impl Entity {
             vy: 0.,
             height,
             width,
-            texture,
+            variant,
             entity_type,
         };
     }
 
-    pub fn update(&mut self, grid: &Grid<Pixel>, boid_data: &Vec<BoidData>) -> bool {
+    pub fn update(&mut self, grid: &Grid<Pixel>, wind: &Wind, boid_data: &Vec<BoidData>, delta: f32, rng: &mut fastrand::Rng) -> bool {
         let pixel = grid[(self.y as usize, self.x as usize)];
-        let delta = get_frame_time();
 
         if self.y >= grid.size().0 as f32 || self.y < 0.0 {
             return false;
@@ -58,31 +61,21 @@ impl Entity {
             return false;
         }
 
-        let physics = match self.entity_type {
-            EntityType::Fish {..}|
-            EntityType::Boid => true,
-         _ => false
-        };
-
-        let terrain_hit = physics::make_map_box(
-            grid, 
-            Rect::new(self.x - 5.0, self.y - 5.0, 10.0, 10.0), 
-            true, 
-            self.x, 
-            self.y
-        );
+        if self.entity_type.has_physics() {
+            let terrain_hit = physics::make_map_box(
+                grid, 
+                (self.x - 5.0, self.y - 5.0, 10.0, 10.0), 
+                true, 
+                self.x, 
+                self.y
+            );
 
-        if physics {
             let mut remaining = delta;
 
             while remaining > 0.0 {
                 let dp = Vec2::new(self.vx, self.vy) * remaining;
-    
-                /*let collision = self
-                    .get_player_box(0.0, 0.0)
-                    .get_collision_with(&terrain_hit, dp);*/
 
-                let bb = physics::make_bounding_box(Rect::new(self.x, self.y, self.texture.width() * self.entity_type.scale(), self.texture.height() * self.entity_type.scale()));
+                let bb = physics::make_bounding_box((self.x, self.y, self.width, self.height));
                 let collision = bb.get_collision_with(&terrain_hit, dp);
 
                 match collision {
@@ -141,12 +134,14 @@ impl Entity {
                 const SPEED: f32 = 6.0;
                 const FOLLOW: f32 = 3.0;
                 const MOMENTUM: f32 = 8.0;
+                /// speed a wind of 1.0 adds
+                const BLOWN: f32 = 4.0;
 
                 self.vx *= MOMENTUM;
                 self.vy += MOMENTUM;
 
-                self.vx += (fastrand::f32() - 0.5) * RANDOMNESS;
-                self.vy +=(fastrand::f32() - 0.5) * RANDOMNESS;
+                self.vx += (rng.f32() - 0.5) * RANDOMNESS;
+                self.vy +=(rng.f32() - 0.5) * RANDOMNESS;
 
                 for data in boid_data {
 
@@ -187,24 +182,28 @@ impl Entity {
 
                 self.vx /= h;
                 self.vy /= h;
+
+                let (wind_x, wind_y) = wind.at(self.y as usize, self.x as usize);
+                self.vx += wind_x * BLOWN;
+                self.vy += wind_y * BLOWN;
                 // self.vx = 0.1;
                 // self.vy = 0.1;
 
 
             },
             EntityType::Fish{air} => {
-                
-                
-                
+                // acid kills fish outright
+                let air = if pixel == Pixel::Acid { 0.0 } else { air };
+
                 if pixel.is_airy() {
                     self.vy = 5.0;
                     self.entity_type = EntityType::Fish { air: air-delta*5.0 };
                 }else {
-                    if fastrand::f32() > 0.99 {
-                        self.vx += (fastrand::f32() - 0.5) * 10.0
+                    if rng.f32() > 0.99 {
+                        self.vx += (rng.f32() - 0.5) * 10.0
                     }
-                    if fastrand::f32() > 0.99 {
-                        self.vy += (fastrand::f32() - 0.5) * 10.0
+                    if rng.f32() > 0.99 {
+                        self.vy += (rng.f32() - 0.5) * 10.0
                     }
 
                     self.vx *= 0.9;
@@ -212,8 +211,8 @@ impl Entity {
                 }
 
                 if air <= 0.0 {
-                    let new = Entity::new(EntityType::Soul, self.x, self.y);
-                    self.texture = new.texture;
+                    let new = Entity::new(EntityType::Soul, self.x, self.y, rng);
+                    self.variant = new.variant;
                     self.height = new.height;
                     self.width = new.width;
                     self.entity_type = new.entity_type;
@@ -226,6 +225,15 @@ impl Entity {
         
         return true;
     }
+
+    /// adds to the entity's speed, e.g. from a blast. things that don't move
+    /// on their own (trees, souls) aren't pushed
+    pub fn push(&mut self, vx: f32, vy: f32) {
+        if self.entity_type.has_physics() {
+            self.vx += vx;
+            self.vy += vy;
+        }
+    }
 }
 
 
@@ -239,6 +247,16 @@ pub enum EntityType {
 
 /// (width, height)
 impl EntityType {
+    /// whether it moves by its speed and bumps into terrain
+    pub fn has_physics(&self) -> bool {
+        match self {
+            EntityType::Fish {..}|
+            EntityType::Boid => true,
+         _ => false
+        }
+    }
+
+
     pub fn scale(&self) -> f32 {
         match self {
             EntityType::Tree => 1.0/4.0,
@@ -249,31 +267,13 @@ impl EntityType {
     }
     
     
-    fn texture(&self) -> Texture2D {
-        
-        Texture2D::from_file_with_format(
-            (
-                match self {
-                EntityType::Tree => fastrand::choice(vec![
-                    include_bytes!("textures/tree/tree1.png").to_vec(),
-                    include_bytes!("textures/tree/tree2.png").to_vec()
-                    ]),
-                EntityType::Soul => fastrand::choice(vec![
-                    include_bytes!("textures/soul/soul1.png").to_vec(),
-                    ]),
-                EntityType::Fish{air:_} => fastrand::choice(vec![
-                    include_bytes!("textures/fish/fish1.png").to_vec(),
-                    ]),
-                EntityType::Boid => fastrand::choice(vec![
-                    include_bytes!("textures/fish/fish1.png").to_vec(),
-                    ]),
-                
-                }
-        ).unwrap_or(include_bytes!("textures/error.png").to_vec()).as_slice(),
-            None,
-        )
+    /// (width, height) in pixels of each sprite the renderer has for this type
+    pub fn sprite_sizes(&self) -> &'static [(f32, f32)] {
+        match self {
+            EntityType::Tree => &[(8.0, 24.0), (8.0, 16.0)],
+            EntityType::Soul => &[(10.0, 15.0)],
+            EntityType::Fish{air:_} => &[(8.0, 5.0)],
+            EntityType::Boid => &[(8.0, 5.0)],
+        }
     }
-
-
-    
 }
@@ -0,0 +1,86 @@
+use crate::map::{Map, Pixel};
+
+/// ticks between an explosive being caught in a blast and going off itself
+const FUSE: u64 = 4;
+/// speed a blast gives entities per point of power that reaches them
+const ENTITY_PUSH: f32 = 3.0;
+
+/// an explosion that has gone off, kept until the player and entities have
+/// been pushed by it
+#[derive(Clone, Copy, Debug)]
+pub struct Blast {
+    pub row: usize,
+    pub col: usize,
+    pub strength: f32,
+}
+
+impl Blast {
+    /// how much of the blast reaches a point. it loses 1 for every pixel it
+    /// travels and the blast resistance of everything in the way, so
+    /// terrain shelters whatever is behind it
+    pub fn power_at(&self, row: f32, col: f32, get: impl Fn((usize, usize)) -> Pixel) -> f32 {
+        let (from_row, from_col) = (self.row as f32 + 0.5, self.col as f32 + 0.5);
+        let (d_row, d_col) = (row - from_row, col - from_col);
+        let distance = d_row.hypot(d_col);
+        let mut power = self.strength - distance;
+
+        let target = (row as usize, col as usize);
+        for step in 1..distance.ceil() as usize {
+            if power <= 0.0 {
+                break;
+            }
+            let t = step as f32 / distance;
+            let pos = ((from_row + d_row * t) as usize, (from_col + d_col * t) as usize);
+            if pos != target && pos != (self.row, self.col) {
+                power -= get(pos).material().blast_resistance;
+            }
+        }
+        power.max(0.0)
+    }
+
+    /// which way a blast pushes something at a point, as (x, y) of length 1
+    pub fn direction(&self, row: f32, col: f32) -> (f32, f32) {
+        let (d_row, d_col) = (row - self.row as f32 - 0.5, col - self.col as f32 - 0.5);
+        let distance = d_row.hypot(d_col).max(0.01);
+        (d_col / distance, d_row / distance)
+    }
+}
+
+impl Map {
+    /// sets explosives caught in a blast to go off a few ticks later, so a
+    /// chain of them goes off over time instead of all in one go
+    pub fn light_fuse(&mut self, row: usize, col: usize) {
+        if !self.fuses.iter().any(|(_, r, c)| (*r, *c) == (row, col)) {
+            self.fuses.push((self.tick + FUSE, row, col));
+        }
+    }
+
+    /// sets off every explosive whose fuse has burnt down
+    pub fn burn_fuses(&mut self) {
+        let tick = self.tick;
+        let (due, waiting) = std::mem::take(&mut self.fuses)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _, _)| *at <= tick);
+        self.fuses = waiting;
+
+        for (_, row, col) in due {
+            // it might have been mined or blown away since
+            if let Some(strength) = self.grid[(row, col)].material().explosion_strength {
+                self.with_worker(|worker| worker.explode(row, col, strength));
+            }
+        }
+    }
+
+    /// pushes every entity the blast reaches away from it
+    pub fn push_entities(&mut self, blast: Blast) {
+        let grid = &self.grid;
+        for entity in &mut self.entities {
+            let (row, col) = (entity.y + entity.height / 2.0, entity.x + entity.width / 2.0);
+            let power = blast.power_at(row, col, |pos| grid.get(pos.0, pos.1).copied().unwrap_or(Pixel::Bedrock));
+            if power > 0.0 {
+                let (x, y) = blast.direction(row, col);
+                entity.push(x * power * ENTITY_PUSH, y * power * ENTITY_PUSH);
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ use std::{
 use crate::{
     map::Map,
     player::{self, Inventory, Item, Player},
+    settings::Settings,
 };
 
 use egui_macroquad::{
@@ -55,6 +56,7 @@ impl Player {
                             ui.label("*");
                         }
                     });
+                    ui.label(&format!("{} - {}", map.clock.season(), map.weather.kind));
                     self.hover_ui = egui_ctx.is_pointer_over_area();
                 });
             egui::Window::new("")
@@ -65,6 +67,8 @@ impl Player {
                     ui.label(&format!("FPS: {}", get_fps()));
                     ui.label(&format!("X / Y: {} {}", self.x, self.y));
                     ui.label(&format!("BIOME {}", map.biome));
+                    let (hours, minutes) = map.clock.hours_minutes();
+                    ui.label(&format!("DAY {} {:02}:{:02}", map.clock.day + 1, hours, minutes));
 
                     self.hover_ui = egui_ctx.is_pointer_over_area();
                 });
@@ -111,7 +115,11 @@ impl Player {
                 .id(Id::new("option"))
                 .anchor(Align2::LEFT_CENTER, [10.0, 0.0])
                 .show(egui_ctx, |ui| {
-                    ui.label(format!("CURRENTLY HOLDING: {}", self.item_in_hand));
+                    let holding = match self.item_in_hand {
+                        Item::Pickaxe => self.pickaxe_name().to_owned(),
+                        _ => self.item_in_hand.to_string(),
+                    };
+                    ui.label(format!("CURRENTLY HOLDING: {holding}"));
                     ui.label("");
 
                     if ui
@@ -140,6 +148,13 @@ impl Player {
                     {
                         equip_item = Some(Item::Pickaxe)
                     }
+                    if let Some((name, ingot, cost)) = self.next_pickaxe() {
+                        if matches!(self.item_in_hand, Item::Pickaxe)
+                            && ui.button(format!("  > Upgrade to {name} ({cost} {})", ingot.material().label)).clicked()
+                        {
+                            self.upgrade_pickaxe();
+                        }
+                    }
                     if ui
                         .button(
                             " > Place".to_owned()
@@ -323,7 +338,6 @@ pub async fn terminal() -> (Map, Player) {
                                 let mut final_player = Player::new("debug".to_owned());
                                 let mut final_map = Map::new_square(200, "debug".to_owned());
                                 final_map.gen_terrain();
-                                final_map.update_image();
                                 final_player.inventory = Inventory::creative();
                                 let respawn_point =
                                     Vec2::new(final_map.size as f32 / 2.0 - 1.0, 4.0);
@@ -340,7 +354,6 @@ pub async fn terminal() -> (Map, Player) {
                                 for ((row, col), _) in final_map.grid.indexed_iter() {
                                     final_map.update_texture_px.insert((row, col));
                                 }
-                                final_map.update_image();
                                 map = Some(final_map);
                                 player = Some(final_player);
                                 // return (final_map, final_player);
@@ -392,9 +405,9 @@ pub async fn terminal() -> (Map, Player) {
                             };
                             ui.label(" ");
                             if ui.button("> Launch").clicked() {
-                                fastrand::seed(hash(seed.clone()));
                                 let mut final_player = Player::new(name.clone());
-                                let mut final_map = Map::new_square(size_int, name.clone());
+                                let mut final_map =
+                                    Map::new_seeded(size_int, name.clone(), hash(seed.clone()));
                                 final_map.gen_terrain();
                                 final_player.inventory = if creative {
                                     Inventory::creative()
@@ -411,7 +424,6 @@ pub async fn terminal() -> (Map, Player) {
                                 for ((row, col), _) in final_map.grid.indexed_iter() {
                                     final_map.update_texture_px.insert((row, col));
                                 }
-                                final_map.update_image();
                                 map = Some(final_map);
                                 player = Some(final_player);
                             }
@@ -445,7 +457,6 @@ pub async fn terminal() -> (Map, Player) {
                                         for ((row, col), _) in final_map.grid.indexed_iter() {
                                             final_map.update_texture_px.insert((row, col));
                                         }
-                                        final_map.update_image();
                                         map = Some(final_map);
                                         player = Some(final_player);
                                     }
@@ -494,3 +505,102 @@ pub async fn terminal() -> (Map, Player) {
         player.unwrap_or_default(),
     );
 }
+
+pub async fn settings_ui(settings: &mut Settings, blur_material: Option<Material>) {
+
+    let mut sim_distance_string: String = settings.sim_distance.to_string();
+    let mut min_fps_string: String = settings.min_fps.to_string();
+    let mut day_length_string: String = settings.day_length.to_string();
+
+    // let mut text_edit_number = 0;
+
+    let background = get_screen_data();
+
+    // background.bytes = gaussian_blur::blur(background.height(), background.width(), [1, 14, 62, 102, 62, 14, 1], background.bytes).0;
+
+    let background_texture = Texture2D::from_image(&background);
+
+    while settings.open{
+        clear_background(LIGHTGRAY);
+
+        if let Some(mat) = blur_material {
+        gl_use_material(mat);
+        mat.set_uniform("textureSize", (screen_width(), screen_height()));
+        draw_texture_ex(background_texture, 0.0, 0.0, WHITE, DrawTextureParams {
+            dest_size: Some(Vec2{y:screen_height(), x:screen_width()}),
+            source: None,
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: true,
+            pivot: None,
+        });
+        
+        }
+
+        egui_macroquad::ui(|egui_ctx| {
+            egui::Window::new("Settings")
+                .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+                .scroll2([false,true])
+                .collapsible(false)
+                .fixed_size([screen_width(),screen_height()])
+                .open(&mut settings.open)
+                .show(egui_ctx, |ui| {
+                    // ui.label();
+                    
+                    ui.vertical(|ui| {
+                    
+                    
+                    
+                    if ui
+                            .button(format!(
+                                "[{}] Dynamic Simulation Distance",
+                                if settings.dynamic_simulation_distance { "x" } else { " " }
+                            ))
+                            .clicked()
+                        {
+                            settings.dynamic_simulation_distance = !settings.dynamic_simulation_distance
+                        };
+
+                    // edit block
+                    ui.horizontal(|ui| {
+                        ui.label("Dynamic simulation distance: [");
+                        if ui.text_edit_singleline(&mut sim_distance_string).lost_focus() {
+                            if let Ok(num) = sim_distance_string.parse::<i32>() {
+                                settings.sim_distance = num;
+                            }
+                            sim_distance_string = settings.sim_distance.to_string();
+                        };
+                        ui.label("]")
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Minimum FPS: [");
+                        if ui.text_edit_singleline(&mut min_fps_string).lost_focus() {
+                            if let Ok(num) = min_fps_string.parse::<i32>() {
+                                settings.min_fps = num;
+                            }
+                            min_fps_string = settings.min_fps.to_string();
+                        };
+                        ui.label("]")
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Day length (ticks): [");
+                        if ui.text_edit_singleline(&mut day_length_string).lost_focus() {
+                            if let Ok(num) = day_length_string.parse::<u32>() {
+                                settings.day_length = num.max(1);
+                            }
+                            day_length_string = settings.day_length.to_string();
+                        };
+                        ui.label("]")
+                    });
+                });
+
+                });
+            });
+
+        egui_macroquad::draw();
+
+        next_frame().await;
+    }
+}
@@ -0,0 +1,27 @@
+//! the simulation side of the game.
+//!
+//! nothing in here is allowed to touch macroquad or egui so the world can be
+//! stepped without a window (tests, servers, batch tools). drawing lives in
+//! the binary, see `render.rs`.
+
+pub mod chunk;
+pub mod clock;
+pub mod components;
+pub mod craft;
+pub mod entity;
+pub mod explosion;
+pub mod light;
+pub mod map;
+pub mod materials;
+pub mod particles;
+pub mod physics;
+pub mod pickaxe;
+pub mod rng;
+pub mod season;
+pub mod settings;
+pub mod sun;
+pub mod update;
+pub mod weather;
+pub mod wind;
+
+pub const SAVEFILE_VERSION: u32 = 9;
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+use crate::map::Map;
+
+/// furthest any material's light can reach, in pixels
+pub const MAX_LIGHT_RADIUS: usize = 16;
+/// light dimmer than this on every channel isn't spread any further
+const MIN_GLOW: f32 = 0.02;
+/// light spreads diagonally too so it comes out round rather than as a
+/// diamond
+const NEIGHBOURS: [(i32, i32); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+impl Map {
+    /// works out the coloured light in an area again, flooding out from
+    /// everything that gives off light in or near it. light fades out over
+    /// its radius and is tinted by whatever it goes through on the way.
+    /// cells whose light changed are marked to be redrawn
+    pub fn update_glow(&mut self, rows: RangeInclusive<usize>, cols: RangeInclusive<usize>) {
+        let size = self.size as usize;
+        // lights just outside the area still shine into it
+        let area = GlowArea {
+            top: rows.start().saturating_sub(MAX_LIGHT_RADIUS),
+            left: cols.start().saturating_sub(MAX_LIGHT_RADIUS),
+            bottom: (rows.end() + MAX_LIGHT_RADIUS).min(size - 1),
+            right: (cols.end() + MAX_LIGHT_RADIUS).min(size - 1),
+        };
+        let mut glow = vec![[0.0; 3]; area.width() * area.height()];
+
+        for row in area.top..=area.bottom {
+            for col in area.left..=area.right {
+                let Some((color, radius)) = self.grid[(row, col)].material().light else {
+                    continue;
+                };
+                // the inside of a big fire or lake of lava is lit by its own
+                // light, only the edges need to flood out
+                let buried = [(row.wrapping_sub(1), col), (row + 1, col), (row, col.wrapping_sub(1)), (row, col + 1)]
+                    .into_iter()
+                    .all(|pos| self.grid.get(pos.0, pos.1).is_some_and(|px| px.material().light.is_some()));
+                if buried {
+                    let cell = &mut glow[area.index((row, col))];
+                    *cell = max_glow(*cell, color);
+                } else {
+                    self.flood_glow(&area, &mut glow, (row, col), color, radius as usize);
+                }
+            }
+        }
+
+        for row in rows {
+            for col in cols.clone() {
+                let light = glow[area.index((row, col))];
+                if self.glow[(row, col)] != light {
+                    self.glow[(row, col)] = light;
+                    self.update_light_px.insert((row, col));
+                }
+            }
+        }
+    }
+
+    /// spreads one light out from `from`, keeping the brightest light each
+    /// cell gets from anything. it fades with the length of the way it took,
+    /// so it only bends round corners by going the long way
+    fn flood_glow(&self, area: &GlowArea, glow: &mut [[f32; 3]], from: (usize, usize), color: [f32; 3], radius: usize) {
+        // how much of each colour has made it to each cell around the light,
+        // and how far it went to get there
+        let side = radius * 2 + 1;
+        let mut through = vec![([0.0; 3], 0.0); side * side];
+        let local = |pos: (usize, usize)| (pos.0 + radius - from.0) * side + pos.1 + radius - from.1;
+        let brightness = |(reached, travelled): ([f32; 3], f32)| {
+            let falloff = (1.0 - travelled / (radius as f32 + 1.0)).max(0.0);
+            [0, 1, 2].map(|c| color[c] * reached[c] * falloff)
+        };
+
+        through[local(from)] = ([1.0; 3], 0.0);
+        let mut queue = VecDeque::from([from]);
+        while let Some(pos) = queue.pop_front() {
+            let (reached, travelled) = through[local(pos)];
+            let cell = &mut glow[area.index(pos)];
+            *cell = max_glow(*cell, brightness((reached, travelled)));
+
+            // light lights up the surface of whatever stops it
+            let tint = if pos == from { Some([1.0; 3]) } else { self.grid[pos].material().tint };
+            let Some(tint) = tint else {
+                continue;
+            };
+            let onward = [0, 1, 2].map(|c| reached[c] * tint[c]);
+
+            for (d_row, d_col) in NEIGHBOURS {
+                let next = ((pos.0 as i32 + d_row) as usize, (pos.1 as i32 + d_col) as usize);
+                if !area.contains(next) || next.0.abs_diff(from.0) > radius || next.1.abs_diff(from.1) > radius {
+                    continue;
+                }
+                let step = if d_row != 0 && d_col != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+                let light = brightness((onward, travelled + step));
+                if light.iter().all(|c| *c < MIN_GLOW) {
+                    continue;
+                }
+                let before = brightness(through[local(next)]);
+                if light.iter().sum::<f32>() > before.iter().sum::<f32>() + MIN_GLOW {
+                    through[local(next)] = (onward, travelled + step);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// the light reaching a cell from the sky and from things that glow, as
+    /// red, green and blue from 0.0 (dark) to 1.0
+    pub fn light_at(&self, row: usize, col: usize) -> [f32; 3] {
+        let sky = 1.0 - self.light_mask[(row, col)];
+        self.glow[(row, col)].map(|c| (sky + c).min(1.0))
+    }
+}
+
+/// the part of the map a glow update looks at
+struct GlowArea {
+    top: usize,
+    left: usize,
+    bottom: usize,
+    right: usize,
+}
+
+impl GlowArea {
+    fn width(&self) -> usize {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> usize {
+        self.bottom - self.top + 1
+    }
+
+    fn contains(&self, (row, col): (usize, usize)) -> bool {
+        (self.top..=self.bottom).contains(&row) && (self.left..=self.right).contains(&col)
+    }
+
+    fn index(&self, (row, col): (usize, usize)) -> usize {
+        (row - self.top) * self.width() + col - self.left
+    }
+}
+
+fn max_glow(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])]
+}
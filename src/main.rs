@@ -1,41 +1,36 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod entity;
 mod game_ui;
-mod map;
 mod player;
-mod settings;
-mod update;
 mod egui_style;
-mod physics;
+mod render;
 
 use egui_macroquad::{egui::{FontData, FontDefinitions, FontFamily}, macroquad::{self, miniquad::{log, Pipeline}, prelude::*}};
 use egui_style::robot_style;
 use entity::{BoidData, EntityType};
 // mod profiling;
-mod craft;
+use grid_game::{craft, entity, map, physics, pickaxe, settings, update, SAVEFILE_VERSION};
 use crate::craft::craft;
 
-use game_ui::{terminal};
+use game_ui::{settings_ui, terminal};
 use savefile::prelude::*;
 use settings::Settings;
 
 /*use console_error_panic_hook;*/
-use std::{collections::HashSet, env, panic::{self, set_hook}, time::Instant};
+use std::{env, panic::{self, set_hook}, time::Instant};
 
 use egui_macroquad::macroquad::{
     miniquad::{BlendFactor, BlendState, BlendValue, Equation},
     prelude::*,
     ui::{root_ui, Skin, Style},
 };
-use map::{Map, Pixel};
+use map::{Map, Pixel, PixelMeta};
 use player::{Item, Player};
+use render::{draw_hit_lines, pixel_color, MapRenderer};
 
 use backtrace::Backtrace;
 
 
-pub const SAVEFILE_VERSION: u32 = 0;
-
 fn window_conf() -> Conf {
     Conf {
         window_title: "pixel game".to_owned(),
@@ -84,10 +79,11 @@ async fn main() {
                 include_str!("./shader/light_frag.glsl"),
                 MaterialParams {
                     pipeline_params: PipelineParams {
+                        // multiplies the world by the colour of the light
                         color_blend: Some(BlendState::new(
                             Equation::Add,
-                            BlendFactor::Value(BlendValue::SourceAlpha),
-                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                            BlendFactor::Zero,
+                            BlendFactor::Value(BlendValue::SourceColor),
                         )),
                         ..Default::default()
                     },
@@ -235,13 +231,10 @@ async fn main() {
 
     let (mut map, mut player) = terminal().await;
 
-    let mut texture: Texture2D = Texture2D::from_image(&map.image);
-    let mut light_texture: Texture2D = Texture2D::from_image(&map.light_mask);
+    let mut renderer = MapRenderer::new(&map);
 
     let settings = Settings::default();
 
-    texture.set_filter(FilterMode::Nearest);
-
     //light_texture.set_filter(FilterMode::Nearest);
 
     // map.make_square(map::Pixel::Water);
@@ -301,7 +294,8 @@ async fn main() {
         set_camera(&cam);
 
         // clear_background(Color { r: 0.8, g: 0.8, b: 0.8, a: 1.0 });
-        clear_background(SKYBLUE);
+        let (r, g, b) = map.clock.sky_color();
+        clear_background(Color::new(r, g, b, 1.0));
 
         boid_data.clear();
         for entity in &map.entities {
@@ -314,19 +308,15 @@ async fn main() {
                 })
             }
         }
-        if !map.update_texture_px.is_empty() {
-            map.update_image();
-            texture.update(&map.image);
-            map.update_texture_px.clear();
-        }
+        renderer.update(&mut map);
     
         if !paused {
-            map.update_state(&player);
-            map.entities.retain_mut(|x| x.update(&(map.grid), &boid_data));
+            map.adjust_sim_distance(get_fps());
+            map.update_state(&player.sim_focus());
+            player.feel_blasts(&map);
+            map.update_entities(&boid_data, delta);
         }
 
-        light_texture.update(&map.light_mask);
-
         match get_char_pressed() {
             Some('i') => {
                 player.inventory.open = !player.inventory.open;
@@ -360,7 +350,7 @@ async fn main() {
         let wand_rect = player
             .craft_rect(map.size.clone() as usize)
             .unwrap_or_default();
-        let craft_result = craft(map.get_region(wand_rect));
+        let craft_result = craft(map.get_region(wand_rect.x, wand_rect.y, wand_rect.w, wand_rect.h));
         
         
         // for (pos @ (row, col),i) in craft_result.1.indexed_iter() {
@@ -376,11 +366,7 @@ async fn main() {
         //     }
         // }
         
-        if !map.update_texture_px.is_empty() {
-            map.update_image();
-            texture.update(&map.image);
-            map.update_texture_px = HashSet::default();
-        }
+        renderer.update(&mut map);
         
 
         draw_rectangle(0.0, 0.0, map.size as f32, map.size as f32, WHITE);
@@ -390,7 +376,7 @@ async fn main() {
 
         for e in &map.entities {
             draw_texture_ex(
-                e.texture,
+                renderer.entity_texture(e),
                 e.x,
                 e.y - e.height + 1.0,
                 WHITE,
@@ -405,7 +391,7 @@ async fn main() {
             world_material.set_uniform("textureSize", (map.size as f32, map.size as f32));
         }
         draw_texture_ex(
-            texture,
+            renderer.texture,
             0.0,
             0.0,
             WHITE,
@@ -414,6 +400,35 @@ async fn main() {
             },
         );
 
+        for particle in &map.particles {
+            let x = particle.x.floor();
+            let y = particle.y.floor();
+            draw_texture_ex(white_texture, x, y, pixel_color(particle.pixel, particle.meta), DrawTextureParams {
+                source: Some(Rect::new(
+                    x / map.size as f32,
+                    y / map.size as f32,
+                    1.0 / map.size as f32,
+                    1.0 / map.size as f32
+                )),
+                dest_size: Some(Vec2::new(1.0, 1.0)),
+                ..Default::default()
+            });
+        }
+
+        // lightning comes down from above the top of the screen in a zigzag
+        if let Some((row, col)) = map.weather.flash(map.tick) {
+            let top = player.get_view_port().y.min(row as f32) - 1.0;
+            let mut from = Vec2::new(col as f32 + 0.5, top);
+            let steps = ((row as f32 - top) / 4.0).ceil().max(1.0) as usize;
+            for step in 1..=steps {
+                let y = top + (row as f32 + 0.5 - top) * step as f32 / steps as f32;
+                let jitter = if step == steps { 0.0 } else { ((step * 7 + col) % 5) as f32 - 2.0 };
+                let to = Vec2::new(col as f32 + 0.5 + jitter, y);
+                draw_line(from.x, from.y, to.x, to.y, 0.4, WHITE);
+                from = to;
+            }
+        }
+
         for (pos @ (row, col),i) in craft_result.1.indexed_iter() {
             if *i {
                 let x = col as f32 + wand_rect.x;
@@ -423,7 +438,7 @@ async fn main() {
                     
                 } else {
                     //draw_rectangle(col as f32 + wand_rect.x, row as f32 + wand_rect.y, 1.0, 1.0, craft_result.2[pos].color());
-                    draw_texture_ex(white_texture, x, y, craft_result.2[pos].color(), DrawTextureParams {
+                    draw_texture_ex(white_texture, x, y, pixel_color(craft_result.2[pos], PixelMeta::default()), DrawTextureParams {
                         source: Some(Rect::new(
                             x / map.size as f32, 
                             y / map.size as f32, 
@@ -443,7 +458,7 @@ async fn main() {
         }
 
         draw_texture_ex(
-            light_texture,
+            renderer.light_texture,
             0.0,
             0.0,
             WHITE,
@@ -472,23 +487,22 @@ async fn main() {
 
         //let hit = player.make_map_box(&map, player.view_port_cache, false);
         //let hit = player.make_map_box(&map, Rect::new(player.x - 20.0, player.y - 20.0, 40.0, 40.0), true);
-        let hit = physics::make_map_box(&map.grid, player.view_port_cache, false, 0.0, 0.0);
-        hit.render();
+        let view = player.view_port_cache;
+        let hit = physics::make_map_box(&map.grid, (view.x, view.y, view.w, view.h), false, 0.0, 0.0);
+        draw_hit_lines(&hit);
 
         if player.render_ui(&mut map) {
             save_all(&player, &map);
             clear_background(BLACK);
             (map, player) = terminal().await;
 
-            texture = Texture2D::from_image(&map.image);
-            light_texture = Texture2D::from_image(&map.light_mask);
-            texture.set_filter(FilterMode::Nearest);
+            renderer = MapRenderer::new(&map);
             continue;
         };
 
         
 
-        player.get_player_box(0.0, 0.0).render();
+        draw_hit_lines(&player.get_player_box(0.0, 0.0));
 
         // crafting
 
@@ -538,7 +552,9 @@ async fn main() {
         } else {
             match player.item_in_hand {
                 Item::Pickaxe if hover != Some(Pixel::Air) => {
-                    draw_rectangle_lines(pt.x.floor(), pt.y.floor(), 1.0, 1.0, 0.5, RED);
+                    // too hard for the pickaxe the player has
+                    let color = if hover.is_some_and(|px| player.can_mine(px)) { RED } else { DARKGRAY };
+                    draw_rectangle_lines(pt.x.floor(), pt.y.floor(), 1.0, 1.0, 0.5, color);
                 }
                 Item::PlacePixel { pixel: _, count } => {
                     draw_rectangle_lines(pt.x.floor(), pt.y.floor(), 1.0, 1.0, 0.5, LIGHTGRAY);
@@ -588,7 +604,7 @@ async fn main() {
         next_frame().await;
 
         if map.settings.open {
-            map.settings.settings_ui(blur_material).await;
+            settings_ui(&mut map.settings, blur_material).await;
         }
 
     }
@@ -1,50 +1,91 @@
 
 
 use core::fmt;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
 use std::{collections::HashSet, fmt::Display};
 use std::fs::create_dir_all;
 
 
 use grid::*;
-use egui_macroquad::{macroquad::{
-    color::{Color, WHITE}, math::Rect, texture::Image
-}};
 use savefile::{load_file, save_file};
 use savefile_derive::Savefile;
-use strum_macros::EnumIter;
 
 use perlin2d::PerlinNoise2D;
     
-use crate::game_ui::display_message;
+use crate::chunk::Chunks;
+use crate::clock::Clock;
+use crate::components::{ComponentId, Components};
+use crate::materials::{Behaviour, Material, MATERIALS};
+use crate::particles::Particle;
+use crate::explosion::Blast;
+use crate::rng::MapRng;
 use crate::settings::Settings;
+use crate::sun::Sun;
+use crate::weather::{Weather, WeatherKind};
+use crate::wind::Wind;
+use crate::update::{AMBIENT_TEMPERATURE, FLUID_COMPRESSION};
 use crate::{entity::{Entity, EntityType}, SAVEFILE_VERSION};
 
-// #[repr(C)] 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, Savefile, Hash)]
-pub enum Pixel {
-    Air,
-    Sand,
-    Dirt,
-    Stone,
-    Water,
-    Candle,
-    Fire,
-    Grass,
-    Wood,
-    Bedrock,
-    Smoke,
-    Steam,
-    Gold,
-    Oil,
-    Glass,
-    Lava,
-    Explosive,
-    LiveWood,
-    Seed,
-    Leaf,
-    Lamp,
-    Loot,
+/// set of pixel positions. uses a fixed hasher so it is always walked in the
+/// same order, which keeps the simulation deterministic
+pub type PxSet = HashSet<(usize, usize), BuildHasherDefault<DefaultHasher>>;
+
+/// a material, looked up in `MATERIALS`. the built in ones have constants
+/// so the rules that need a specific material can name it
+#[derive(Copy, Clone, PartialEq, Eq, Savefile, Hash)]
+pub struct Pixel(pub u8);
+
+macro_rules! built_in_pixels {
+    ($($name:ident = $id:literal,)*) => {
+        #[allow(non_upper_case_globals)]
+        impl Pixel {
+            $(pub const $name: Pixel = Pixel($id);)*
+
+            /// names of the built in materials, in id order
+            pub const BUILT_IN: &'static [&'static str] = &[$(stringify!($name)),*];
+        }
+    };
+}
+
+built_in_pixels! {
+    Air = 0,
+    Sand = 1,
+    Dirt = 2,
+    Stone = 3,
+    Water = 4,
+    Candle = 5,
+    Fire = 6,
+    Grass = 7,
+    Wood = 8,
+    Bedrock = 9,
+    Smoke = 10,
+    Steam = 11,
+    Gold = 12,
+    Oil = 13,
+    Glass = 14,
+    Lava = 15,
+    Explosive = 16,
+    LiveWood = 17,
+    Seed = 18,
+    Leaf = 19,
+    Lamp = 20,
+    Loot = 21,
+    Snow = 22,
+    Ice = 23,
+    Cryo = 24,
+    Acid = 25,
+    Coal = 26,
+    IronOre = 27,
+    CopperOre = 28,
+    Iron = 29,
+    Copper = 30,
+    Clay = 31,
+    Mud = 32,
+    Brick = 33,
+    FrozenOil = 34,
+    FrozenAcid = 35,
 }
 
 impl Default for Pixel {
@@ -53,136 +94,128 @@ impl Default for Pixel {
     }
 }
 
+impl fmt::Debug for Pixel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.material().name)
+    }
+}
+
 impl Pixel {
+    pub fn material(&self) -> &'static Material {
+        MATERIALS.get(*self)
+    }
 
+    /// every material, built in ones first
+    pub fn all() -> impl ExactSizeIterator<Item = Pixel> {
+        MATERIALS.pixels()
+    }
 
-    pub fn color(&self) -> Color {
-        match self {
-            Pixel::Air => Color::from_rgba(250, 251, 255, 0),
-            Pixel::Lamp => Color::from_rgba(250, 231, 235, 255),
-            Pixel::Sand => Color::from_rgba(207, 215, 157, 255),
-            Pixel::Fire => Color::from_rgba(193, 84, 45, 255),
-            Pixel::Wood => Color::from_rgba(139, 107, 59, 255),
-            Pixel::LiveWood => Color::from_rgba(139, 107, 59, 255),
-            Pixel::Seed => Color::from_rgba(113,169,44, 155),
-            Pixel::Leaf => Color::from_rgba(113,149,44, 155),
-            Pixel::Smoke => Color::from_rgba(190,190,190, 255),
-            Pixel::Steam => Color::from_rgba(199,213,224, 255),
-            Pixel::Water => Color::from_rgba(35,69,190, 150),
-            Pixel::Dirt => Color::from_rgba(155,118,83, 255),
-            Pixel::Stone => Color::from_rgba(168,169,173, 255),
-            Pixel::Grass => Color::from_rgba(113,169,44, 255),
-            Pixel::Gold => Color::from_rgba(205, 127, 50, 255),
-            Pixel::Lava => Color::from_rgba(247, 104, 6, 255),
-            Pixel::Oil => Color::from_rgba(0, 0, 0, 255),
-            Pixel::Loot => Color::from_rgba(255, 105, 180, 255),
-            Pixel::Candle => Color::from_rgba(239, 230, 211, 255),
-            Pixel::Glass => Color::from_rgba(100, 104, 230, 5),
-            Pixel::Bedrock => Color::from_rgba(fastrand::u8(0..255), fastrand::u8(0..255), fastrand::u8(0..255), 255),
-            Pixel::Explosive => Color::from_rgba(242, 33, 5, 255),
-        }
+    /// rgba colour the pixel is drawn with
+    pub fn color(&self) -> [u8; 4] {
+        self.material().color
     }
 
-    pub fn light_emission(&self) -> Color {
-        match self {
-            Pixel::Air => {Color::new(1.0, 1.0, 1.0, 0.4)},
-            Pixel::Glass => {Color::new(1.0, 1.0, 1.0, 0.4)},
-            Pixel::Steam | Pixel::Smoke => {Color::new(1.0, 1.0, 1.0, 0.4)},
-            Pixel::Water => {Color::new(1.0, 1.0, 1.0, 0.5)},
-            Pixel::Lava => {Color::new(1.0, 0.0, 0.0, 0.0)},
-            Pixel::Lamp => {Color::new(1.0, 0.0, 0.0, 0.0)},
-            Pixel::Fire => {Color::new(1.0, 0.0, 0.0, 0.0)},
-            Pixel::Leaf => {Color::new(0.0, 1.0, 0.0, 0.5)},
-            Pixel::Loot => {Color::new(0.0, 1.0, 0.0, 0.3)},
-            _ => {Color::new(0.0, 0.0, 0.0, 1.0)}
-        }
+    /// how much sky light fades going through the pixel, 0.0 not at all and
+    /// 1.0 the most
+    pub fn light_emission(&self) -> f32 {
+        self.material().darkness
     }
 
     pub fn fluid(&self) -> bool {
-        matches!(self , Pixel::Lava | Pixel::Water | Pixel::Oil)
+        self.material().fluid
     }
 
-    
+    pub fn gas(&self) -> bool {
+        matches!(self.material().behaviour, Behaviour::Gas { .. })
+    }
+
+    /// light goes through it, maybe tinted, see `Material::tint`
+    pub fn lets_light_through(&self) -> bool {
+        self.material().tint.is_some()
+    }
+
+    /// liquids and gases, which keep track of how much of them is in a cell
+    pub fn flows(&self) -> bool {
+        self.fluid() || self.gas()
+    }
 
     pub fn is_airy(&self) -> bool {
-        return matches!(self, Pixel::Air | Pixel::Fire | Pixel::Smoke | Pixel::Steam);
+        self.material().airy
     }
 
     pub fn fluid_density(&self) -> Option<i32> {
-        match self {
-            Pixel::Air => Some(3),
-            Pixel::Sand|Pixel::Dirt|Pixel::Lava|Pixel::Grass|Pixel::Explosive| Pixel::Seed => Some(30),
-            Pixel::Smoke => Some(1),
-            Pixel::Steam => Some(1),
-            Pixel::Water => Some(15),
-            Pixel::Oil => Some(10),
-            Pixel::Fire => Some(2),
-            Pixel::Bedrock
-            |Pixel::Wood 
-            |Pixel::Lamp 
-            |Pixel::Leaf 
-            |Pixel::LiveWood 
-            |Pixel::Stone
-            |Pixel::Loot
-            |Pixel::Candle
-            |Pixel::Glass
-            |Pixel::Gold => None,
-        }
+        self.material().density
     }
 
-    pub fn heat_product(&self) -> Option<Self> {
-        match self {
-            Pixel::Wood => Some(Pixel::Fire),
-            Pixel::LiveWood => Some(Pixel::Fire),
-            Pixel::Oil => Some(Pixel::Fire),
-            Pixel::Sand => Some(Pixel::Glass),
-            Pixel::Water => Some(Pixel::Steam),
-            Pixel::Leaf => if fastrand::f32() > 0.95 {Some(Pixel::Seed)} else {Some(Pixel::Fire)},
-            _ => None
-        }
+    pub fn heat_product(&self, rng: &mut fastrand::Rng) -> Option<Self> {
+        self.material()
+            .heats_into
+            .iter()
+            .find(|(_, chance)| *chance >= 1.0 || rng.f32() < *chance)
+            .map(|(product, _)| *product)
     }
 
     pub fn ignition_probability(&self) -> f32 {
-        match self {
-            Pixel::Wood => 5.0,
-            Pixel::LiveWood => 40.0,
-            Pixel::Leaf => 40.0,
-            Pixel::Oil => 20.0,
-            Pixel::Water => 50.0,
-            Pixel::Sand => 0.01,
-            Pixel::Explosive => 100.0,
-            _ => 0.0
-        }
-    }
-
-    pub fn extinguish_fire(&self) -> bool {
-        match self {
-            Pixel::Water => true,
-            _ => false
-        }
+        self.material().ignition
     }
 
     pub fn player_damage(&self) -> f32 {
-        match self {
-            Self::Fire => 1.0,
-            Self::Steam => 0.1,
-            Self::Lava => 10.0,
-            _ => 0.0,
-        }
-    } 
+        self.material().player_damage
+    }
 
     pub fn can_hit(&self) -> bool {
-        match self {
-            Pixel::Loot | Pixel::Candle | Pixel::Glass |Pixel::Sand | Pixel::Dirt | Pixel::Bedrock | Pixel::Wood | Pixel::Stone | Pixel::Gold | Pixel::Grass | Pixel::Explosive => true,
-            Pixel::Lamp | Pixel::LiveWood | Pixel::Leaf | Pixel::Seed | Pixel::Oil |Pixel::Air | Pixel::Lava | Pixel::Steam | Pixel::Water | Pixel::Fire | Pixel::Smoke => false
-        }
+        self.material().solid
+    }
+
+    /// solids that don't need holding up, and hold up whatever rests on or
+    /// hangs from them
+    pub fn is_ground(&self) -> bool {
+        self.material().solid && self.material().span.is_none()
     }
 
     pub fn less_dense(&self, p: Pixel) -> bool {
         self.fluid_density().unwrap_or(69) < p.fluid_density().unwrap_or(98)
     }
 
+    /// pixels that can change on their own without anything touching them.
+    /// these keep their chunk awake
+    pub fn is_restless(&self) -> bool {
+        self.material().is_restless()
+    }
+}
+
+/// what a pixel remembers about itself. it moves with the pixel when
+/// pixels are swapped and is saved with the map
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Savefile)]
+pub struct PixelMeta {
+    /// updates since the pixel became what it is
+    pub lifetime: u16,
+    /// how much burning the pixel has left in it
+    pub fuel: u8,
+    /// how wet the pixel is, 255 is soaked
+    pub moisture: u8,
+    /// picked once per cell when the map is made, varies the colour a bit
+    pub color_seed: u8,
+    pub flags: u8,
+}
 
+impl PixelMeta {
+    /// the player put this pixel here
+    pub const PLACED: u8 = 1;
+    /// broke off whatever held it up and is falling
+    pub const LOOSE: u8 = 2;
+
+    /// meta for a pixel that just turned into `pixel`. the colour and how
+    /// wet the spot is stay the same
+    pub fn became(self, pixel: Pixel) -> PixelMeta {
+        PixelMeta {
+            lifetime: 0,
+            fuel: pixel.material().fuel,
+            moisture: self.moisture,
+            color_seed: self.color_seed,
+            flags: 0,
+        }
+    }
 }
 
 pub enum Biome {
@@ -209,7 +242,32 @@ struct MapSave {
     size: u32,
     realistic_fluid: bool,
     name: String,
-
+    #[savefile_versions = "1.."]
+    seed: u64,
+    #[savefile_versions = "1.."]
+    rng_state: Vec<u64>,
+    /// name of the material behind every id in `pixel_vector`, so the map
+    /// still loads after materials are added or removed
+    #[savefile_versions = "2.."]
+    material_names: Vec<String>,
+    #[savefile_versions = "3.."]
+    temperature: Vec<f32>,
+    #[savefile_versions = "4.."]
+    meta: Vec<PixelMeta>,
+    #[savefile_versions = "5.."]
+    fluid: Vec<f32>,
+    /// strength and target of the prevailing wind
+    #[savefile_versions = "6.."]
+    wind: Vec<f32>,
+    #[savefile_versions = "7.."]
+    day: u32,
+    /// older maps start in the morning, like new ones
+    #[savefile_versions = "7.."]
+    #[savefile_default_val = "0.3"]
+    time_of_day: f32,
+    /// kind of weather and ticks until it changes
+    #[savefile_versions = "8.."]
+    weather: Vec<u32>,
 }
 
 impl MapSave {
@@ -219,13 +277,71 @@ impl MapSave {
             pixel_vector: map.grid.clone().into_vec(),
             size: map.size,
             realistic_fluid: map.realistic_fluid,
+            seed: map.seed,
+            rng_state: map.rng.state(),
+            material_names: Pixel::all().map(|px| px.material().name.clone()).collect(),
+            temperature: map.temperature.clone().into_vec(),
+            meta: map.meta.clone().into_vec(),
+            fluid: map.fluid.clone().into_vec(),
+            wind: vec![map.wind.strength, map.wind.target],
+            day: map.clock.day,
+            time_of_day: map.clock.time,
+            weather: vec![map.weather.kind.index(), map.weather.remaining],
         }
     }
 
     fn to_map(self) -> Map {
-        let mut new_map = Map::new_square(self.size as usize, self.name);
+        let mut new_map = Map::new_seeded(self.size as usize, self.name, self.seed);
+        new_map.rng = MapRng::from_state(self.seed, &self.rng_state);
+
+        // saves from before materials were in a file only have built in ones
+        let ids: Vec<Pixel> = if self.material_names.is_empty() {
+            Pixel::all().collect()
+        } else {
+            self.material_names
+                .iter()
+                .map(|name| MATERIALS.by_name(name).unwrap_or(Pixel::Air))
+                .collect()
+        };
+        let pixels = self
+            .pixel_vector
+            .iter()
+            .map(|px| ids.get(px.0 as usize).copied().unwrap_or(Pixel::Air))
+            .collect();
+
+        new_map.grid = Grid::from_vec(pixels, self.size as usize);
+
+        if self.temperature.len() == new_map.grid.rows() * new_map.grid.cols() {
+            new_map.temperature = Grid::from_vec(self.temperature, self.size as usize);
+        } else {
+            new_map.reset_temperature();
+        }
+
+        if self.meta.len() == new_map.grid.rows() * new_map.grid.cols() {
+            new_map.meta = Grid::from_vec(self.meta, self.size as usize);
+        } else {
+            new_map.reset_meta();
+        }
+
+        if self.fluid.len() == new_map.grid.rows() * new_map.grid.cols() {
+            new_map.fluid = Grid::from_vec(self.fluid, self.size as usize);
+        } else {
+            new_map.reset_fluid();
+        }
+        new_map.components.rebuild(&new_map.grid);
+        new_map.reset_stress();
+        new_map.sun.refresh(&new_map.grid, &new_map.clock);
 
-        new_map.grid = Grid::from_vec(self.pixel_vector.clone(), self.size as usize);
+        if let [strength, target] = self.wind[..] {
+            new_map.wind.strength = strength;
+            new_map.wind.target = target;
+        }
+        new_map.wind.refresh(&new_map.grid, new_map.tick);
+        new_map.clock = Clock { day: self.day, time: self.time_of_day };
+        if let [kind, remaining] = self.weather[..] {
+            new_map.weather.kind = WeatherKind::from_index(kind);
+            new_map.weather.remaining = remaining;
+        }
 
         return new_map;
     }
@@ -235,18 +351,49 @@ impl MapSave {
 pub struct Map {
     pub grid: Grid<Pixel>,
     pub size: u32,
-    pub update_texture_px: HashSet::<(usize, usize)>,
-    pub image: Image,
-    pub light_mask: Image,
+    pub update_texture_px: PxSet,
+    /// darkness of every cell, drawn on top of the world
+    pub light_mask: Grid<f32>,
+    /// coloured light from things that give it off, 0.0 - 1.0 for red,
+    /// green and blue. only kept up to date around the view, see
+    /// `Map::update_glow`
+    pub glow: Grid<[f32; 3]>,
+    pub update_light_px: PxSet,
     pub entities: Vec<Entity>,
     pub name: String,
-    pub detected_air: Grid<i16>,
-    pub detected_fluids: Grid<bool>,
+    /// liquids push each other around by pressure, see `PixelWorker::flow_liquid`
     pub realistic_fluid: bool,
-    pub sky_light: Vec<usize>,
+    pub sun: Sun,
     pub block_percent: HashMap<Pixel, i16>,
     pub biome: Biome,
     pub settings: Settings,
+    pub seed: u64,
+    pub rng: MapRng,
+    pub chunks: Chunks,
+    /// number of times `update_state` has run
+    pub tick: u64,
+    /// the tick each cell was last moved on, so nothing moves twice per tick
+    pub updated_on: Grid<u32>,
+    /// temperature of every cell in degrees
+    pub temperature: Grid<f32>,
+    pub meta: Grid<PixelMeta>,
+    /// how much liquid or gas each cell holds, a full cell is 1.0. liquid
+    /// gets squashed by the liquid above it, which is what pushes it back up
+    pub fluid: Grid<f32>,
+    /// bodies of liquid and pockets of air
+    pub components: Components,
+    /// how far each built up pixel is from being held up, see
+    /// `PixelWorker::hold_up`. not saved, it is worked out again on load
+    pub stress: Grid<u8>,
+    pub wind: Wind,
+    pub clock: Clock,
+    pub weather: Weather,
+    /// pixels flying free of the grid, not saved
+    pub particles: Vec<Particle>,
+    /// explosives waiting to go off as (tick, row, col), not saved
+    pub fuses: Vec<(u64, usize, usize)>,
+    /// explosions that went off during the last update, not saved
+    pub blasts: Vec<Blast>,
     // pub heatmap: Image,
 }
 
@@ -294,7 +441,7 @@ impl Map {
     // bias - Amount of change in Perlin noise. U
     0.1, 
     // seed - A value that changes the output of a coherent-noise function.
-    fastrand::i32(0..200)
+    self.rng.worldgen.i32(0..200)
 );
     let perlin2 = PerlinNoise2D::new(
     // octaves - The amount of detail in Perlin noise.
@@ -312,7 +459,7 @@ impl Map {
     // bias - Amount of change in Perlin noise. U
     0.1, 
     // seed - A value that changes the output of a coherent-noise function.
-    fastrand::i32(0..200)
+    self.rng.worldgen.i32(0..200)
 );
     let perlin3 = PerlinNoise2D::new(
     // octaves - The amount of detail in Perlin noise.
@@ -330,8 +477,10 @@ impl Map {
     // bias - Amount of change in Perlin noise. U
     0.1, 
     // seed - A value that changes the output of a coherent-noise function.
-    fastrand::i32(0..200)
+    self.rng.worldgen.i32(0..200)
 );
+    // thin veins of coal and ore running through the stone
+    let veins = PerlinNoise2D::new(5, 10.0, 1.5, 4.0, 2.0, (25.0, 25.0), 0.1, self.rng.worldgen.i32(0..200));
 
         for ((row, col), _) in new_grid.indexed_iter() {
             if row%10 == 0 && col%10 == 0 {
@@ -341,6 +490,8 @@ impl Map {
                 if perlin2 .get_noise(col as f64, row as f64) > 100.0 {
                     self.grid[(row,col)] = Pixel::Sand;
 
+                }else if perlin3.get_noise(col as f64, row as f64) < -800.0 {
+                    self.grid[(row,col)] = Pixel::Clay;
                 }else {
                 self.grid[(row,col)] = Pixel::Dirt;
                 }
@@ -349,7 +500,14 @@ impl Map {
                 
                 if perlin3.get_noise(col as f64, row as f64) > 1200.0 {
                     self.grid[(row,col)] = Pixel::Gold;
-                }else if fastrand::f32() < 0.0005 && row as f32 > self.size as f32 * 0.6 {
+                }else if perlin3.get_noise(col as f64, row as f64) < -1200.0 && row as f32 > self.size as f32 * 0.6 {
+                    self.grid[(row,col)] = Pixel::Cryo;
+                }else if veins.get_noise(col as f64, row as f64) > 1000.0 {
+                    // coal near the surface, copper further down
+                    self.grid[(row,col)] = if (row as f32) < self.size as f32 * 0.5 { Pixel::Coal } else { Pixel::CopperOre };
+                }else if veins.get_noise(col as f64, row as f64) < -1000.0 && row as f32 > self.size as f32 * 0.45 {
+                    self.grid[(row,col)] = Pixel::IronOre;
+                }else if self.rng.worldgen.f32() < 0.0005 && row as f32 > self.size as f32 * 0.6 {
                     self.grid[(row,col)] = Pixel::Loot;
                 } else {
                     self.grid[(row,col)] = Pixel::Stone;
@@ -366,6 +524,9 @@ impl Map {
             if perlin.get_noise(col as f64, row as f64) < -1000.0 {
                 if row as f32 > self.size as f32 * 0.75 {
                 self.grid[(row,col)] = Pixel::Lava;
+            }else if row as f32 <= self.size as f32 * 0.35 && perlin2.get_noise(col as f64, row as f64) < 0.0 {
+                // some of the pools near the surface are frozen over
+                self.grid[(row,col)] = Pixel::Ice;
             }else {
                 self.grid[(row,col)] = Pixel::Water;
             }
@@ -381,7 +542,7 @@ impl Map {
             self.update_texture_px.insert((row, col));
         }
         for ((row, col), _) in new_grid.indexed_iter() {
-            let num = fastrand::u32(0..1000);
+            let num = self.rng.worldgen.u32(0..1000);
             match self.grid[(col,row)] {
                 Pixel::Water => {
                     
@@ -398,14 +559,37 @@ impl Map {
             }
         }
         for i in 2..(self.size -2) {
-            if fastrand::f32() > 0.95 {
+            if self.rng.worldgen.f32() > 0.95 {
             self.grid[((self.size as f32 * 0.22) as usize -1, i as usize)] = Pixel::Seed;
             }
         }
+        // patches of snow left over from the winter before the map starts
+        let surface = (self.size as f32 * 0.22) as usize;
+        for col in 2..(self.size as usize - 2) {
+            if perlin2.get_noise(col as f64, surface as f64) < -800.0 {
+                for row in surface - 3..surface {
+                    if self.grid[(row, col)] == Pixel::Air {
+                        self.grid[(row, col)] = Pixel::Snow;
+                    }
+                }
+            }
+        }
+
+        self.reset_temperature();
+        self.reset_meta();
+        self.reset_fluid();
+        self.components.rebuild(&self.grid);
+        self.reset_stress();
+        self.sun.refresh(&self.grid, &self.clock);
     } 
 
-    /// makes a new square map of the given `usize`
+    /// makes a new square map of the given `usize` with a random seed
     pub fn new_square(size: usize, name: String) -> Map {
+        Map::new_seeded(size, name, fastrand::u64(..))
+    }
+
+    /// makes a new square map whose randomness all comes from `seed`
+    pub fn new_seeded(size: usize, name: String, seed: u64) -> Map {
 
         let mut settings = Settings::default();
 
@@ -417,25 +601,56 @@ impl Map {
         Map {
             grid,
             size: size as u32,
-            update_texture_px: HashSet::default(),
-            image: Image::gen_image_color(size as u16, size as u16, WHITE),
-            light_mask: Image::gen_image_color(size as u16, size as u16, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.3 }),
+            update_texture_px: PxSet::default(),
+            light_mask: Grid::from_vec(vec![0.3; size.pow(2)], size),
+            glow: Grid::from_vec(vec![[0.0; 3]; size.pow(2)], size),
+            update_light_px: PxSet::default(),
             entities: vec![],
-            detected_air: Grid::from_vec(vec![0; size.pow(2) as usize], size as usize),
-            detected_fluids: Grid::from_vec(vec![false; size.pow(2) as usize], size as usize),
             name,
             realistic_fluid: true,
-            sky_light: vec![0;size],
+            sun: Sun::new(size),
             block_percent: HashMap::default(),
             biome: Biome::Surface,
             settings,
+            seed,
+            rng: MapRng::new(seed),
+            chunks: Chunks::new(size),
+            tick: 0,
+            updated_on: Grid::from_vec(vec![0; size.pow(2)], size),
+            temperature: Grid::from_vec(vec![AMBIENT_TEMPERATURE; size.pow(2)], size),
+            meta: Grid::from_vec(vec![PixelMeta::default(); size.pow(2)], size),
+            fluid: Grid::from_vec(vec![0.0; size.pow(2)], size),
+            components: Components::new(size),
+            stress: Grid::from_vec(vec![u8::MAX; size.pow(2)], size),
+            wind: Wind::new(size),
+            clock: Clock::default(),
+            weather: Weather::default(),
+            particles: vec![],
+            fuses: vec![],
+            blasts: vec![],
         }
     }
 
 
     /// add an entity at the given coords
     pub fn spawn_entity(&mut self, entity_type:EntityType, x: f32,y:f32) {
-        self.entities.push(Entity::new(entity_type, x, y));
+        self.entities.push(Entity::new(entity_type, x, y, &mut self.rng.sim));
+    }
+
+    /// what mining a pixel gives. loot boxes roll on the loot stream so they
+    /// can't disturb the rest of the simulation, and only the ones the player
+    /// didn't put there roll at all
+    pub fn mined_drop(&mut self, row: usize, col: usize) -> (Pixel, i32) {
+        let pixel = self.grid[(row, col)];
+        let placed = self.meta[(row, col)].flags & PixelMeta::PLACED != 0;
+        match pixel {
+            Pixel::LiveWood => (Pixel::Wood, 1),
+            Pixel::Loot if !placed => (
+                self.rng.loot.choice(Pixel::all()).unwrap_or(Pixel::Gold),
+                self.rng.loot.i32(10..200),
+            ),
+            _ => (pixel, 1),
+        }
     }
 
     /// makes a square of any malarial in center of map
@@ -452,15 +667,19 @@ impl Map {
                 Pixel::Air
             };
         }
+        self.reset_fluid();
+        self.components.rebuild(&self.grid);
+        self.reset_stress();
+        self.sun.refresh(&self.grid, &self.clock);
     }
     
 
-    pub fn get_region(&self, rect: Rect) -> Grid<Pixel> {
-        let low_col = (rect.left().floor() as i64).clamp(0, self.size as i64 - 1) as usize;
-        let hi_col = (rect.right().ceil() as i64).clamp(0, self.size as i64) as usize;
+    pub fn get_region(&self, x: f32, y: f32, w: f32, h: f32) -> Grid<Pixel> {
+        let low_col = (x.floor() as i64).clamp(0, self.size as i64 - 1) as usize;
+        let hi_col = ((x + w).ceil() as i64).clamp(0, self.size as i64) as usize;
 
-        let low_row = (rect.top().floor() as i64).clamp(0, self.size as i64 - 1) as usize;
-        let hi_row = (rect.bottom().ceil() as i64).clamp(0, self.size as i64) as usize;
+        let low_row = (y.floor() as i64).clamp(0, self.size as i64 - 1) as usize;
+        let hi_row = ((y + h).ceil() as i64).clamp(0, self.size as i64) as usize;
 
         let mut grid = Grid::new(hi_row - low_row, hi_col - low_col);
 
@@ -473,18 +692,122 @@ impl Map {
         return grid;
     }
 
-    /// updates the image based on the pixels listed in the 'update_texture_px' list
-    pub fn update_image(&mut self) {
-        for (row, col) in &self.update_texture_px {
-            self.image.set_pixel(
-                *col as u32,
-                *row as u32,
-                self.grid[(*row, *col)].color(),
-            );
+    /// puts every cell back to the temperature its material sits at on its
+    /// own, for maps that were just made
+    pub fn reset_temperature(&mut self) {
+        for ((row, col), px) in self.grid.indexed_iter() {
+            self.temperature[(row, col)] = px.material().heat.map_or(AMBIENT_TEMPERATURE, |(heat, _)| heat);
+        }
+    }
 
-            if row > &2 && self.sky_light[*col] > *row && !(self.grid[(*row, *col)].is_airy() || self.grid[(*row, *col)] == Pixel::Glass) {
-                self.sky_light[*col]  = *row - 1;
+    /// gives every cell a new colour seed and the fuel its material starts
+    /// with, for maps that were just made
+    pub fn reset_meta(&mut self) {
+        for ((row, col), px) in self.grid.indexed_iter() {
+            self.meta[(row, col)] = PixelMeta {
+                color_seed: self.rng.worldgen.u8(..),
+                ..PixelMeta::default()
             }
+            .became(*px);
         }
     }
-}
+
+    /// fills every liquid cell as full as the liquid above it squashes it,
+    /// so lakes in a map that was just made are already settled. gas cells
+    /// start full
+    pub fn reset_fluid(&mut self) {
+        let mut depth = vec![0; self.size as usize];
+        for ((row, col), px) in self.grid.indexed_iter() {
+            depth[col] = if row > 0 && px.fluid() && self.grid[(row - 1, col)] == *px {
+                depth[col] + 1
+            } else {
+                0
+            };
+            self.fluid[(row, col)] = if px.fluid() {
+                1.0 + depth[col] as f32 * FLUID_COMPRESSION
+            } else if px.gas() {
+                1.0
+            } else {
+                0.0
+            };
+        }
+    }
+
+    /// replaces a pixel, redrawing it and waking everything around it
+    pub fn set_px(&mut self, row: usize, col: usize, pixel: Pixel) {
+        self.grid[(row, col)] = pixel;
+        self.meta[(row, col)] = self.meta[(row, col)].became(pixel);
+        self.fluid[(row, col)] = if pixel.flows() { 1.0 } else { 0.0 };
+        if let Some((heat, _)) = pixel.material().heat {
+            self.temperature[(row, col)] = self.temperature[(row, col)].max(heat);
+        }
+        self.stress[(row, col)] = u8::MAX;
+        if !pixel.lets_light_through() {
+            self.sun.block(row, col);
+        }
+        self.update_texture_px.insert((row, col));
+        self.components.changed((row, col));
+        self.chunks.wake(row, col);
+    }
+
+    /// works out how far every built up pixel is from being held up, see
+    /// `PixelWorker::hold_up`. anything that is already too far out breaks
+    /// loose on its first update, the same way sand falls into caves when a
+    /// map is made
+    pub fn reset_stress(&mut self) {
+        let size = self.size as usize;
+        let mut queue = VecDeque::new();
+        for ((row, col), px) in self.grid.indexed_iter() {
+            let on_floor = row >= size - 3 && px.material().span.is_some();
+            if (px.is_ground() || on_floor) && self.meta[(row, col)].flags & PixelMeta::LOOSE == 0 {
+                self.stress[(row, col)] = 0;
+                queue.push_back((row, col));
+            } else {
+                self.stress[(row, col)] = u8::MAX;
+            }
+        }
+
+        // resting on something is free and anything else costs 1, so cells
+        // reached for free go to the front of the queue
+        while let Some((row, col)) = queue.pop_front() {
+            let stress = self.stress[(row, col)];
+            for (next, cost) in [((row.wrapping_sub(1), col), 0), ((row + 1, col), 1), ((row, col.wrapping_sub(1)), 1), ((row, col + 1), 1)] {
+                let Some(px) = self.grid.get(next.0, next.1) else {
+                    continue;
+                };
+                if px.material().span.is_none()
+                    || self.meta[next].flags & PixelMeta::LOOSE != 0
+                    || self.stress[next] <= stress.saturating_add(cost)
+                {
+                    continue;
+                }
+                self.stress[next] = stress.saturating_add(cost);
+                if cost == 0 {
+                    queue.push_front(next);
+                } else {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// the body of liquid a cell is part of
+    pub fn fluid_body(&self, row: usize, col: usize) -> Option<ComponentId> {
+        self.components.at((row, col)).filter(|_| self.grid[(row, col)].fluid())
+    }
+
+    /// the pocket of air a cell is part of
+    pub fn air_pocket(&self, row: usize, col: usize) -> Option<ComponentId> {
+        self.components.at((row, col)).filter(|_| self.grid[(row, col)] == Pixel::Air)
+    }
+
+    /// hands out the pixels changed since the last call so they can be redrawn
+    pub fn drain_dirty_px(&mut self) -> PxSet {
+        std::mem::take(&mut self.update_texture_px)
+    }
+
+    /// whether the sun (or moon) reaches a pixel
+    pub fn in_sunlight(&self, row: usize, col: usize) -> bool {
+        self.sun.lights(row, col)
+    }
+}
\ No newline at end of file
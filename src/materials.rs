@@ -0,0 +1,439 @@
+//! every material the world can be made of.
+//!
+//! the built in set lives in `materials.ron` and is compiled into the game.
+//! a `materials.ron` in the folder the game runs from is used instead, so
+//! materials can be added and tuned without rebuilding. the file has to
+//! keep every built in material, new ones are added after them.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+use crate::chunk::{IGNITE_REACH, MAX_REACH};
+use crate::light::MAX_LIGHT_RADIUS;
+use crate::map::Pixel;
+
+pub const MATERIALS_PATH: &str = "materials.ron";
+
+const BUILT_IN_MATERIALS: &str = include_str!("materials.ron");
+
+lazy_static! {
+    pub static ref MATERIALS: Materials = Materials::load();
+}
+
+/// what a material does when it gets updated, the rules live in `update.rs`.
+/// `P` is the name of another material in the file and a `Pixel` once loaded
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Behaviour<P> {
+    /// does nothing on its own
+    Static,
+    /// falls and slides off piles of itself, held back as often as its
+    /// `viscosity`
+    Powder,
+    /// a powder that packs down into `packs_into` under a deep enough pile
+    /// of itself
+    Drift { packs_into: P },
+    /// only slides when packed in by itself, grows `grows_into` when open to
+    /// air. soaks up moisture from wet things around it and slowly dries
+    Soil { grows_into: P },
+    /// turns back into `reverts_to` when covered or left hanging
+    Cover { reverts_to: P },
+    /// spreads sideways into anything lighter, as often as its `viscosity`
+    /// and as far as its `dispersion` let it
+    Liquid,
+    /// rises through air as much as it is lighter than it, runs along
+    /// ceilings and spreads into the air around it. loses `fade` of what is
+    /// in a cell each update and is gone once too little is left
+    Gas { fade: f32 },
+    /// sets fire to its neighbours, then burns out into `burns_out_to` once
+    /// its fuel is used up
+    Fire { burns_out_to: P },
+    /// a liquid that sets fire to its neighbours
+    Molten,
+    /// grows more of itself upwards and a ball of `leaf` at the top
+    Plant { leaf: P },
+    /// turns into `sprouts_into` when sitting on `soil`
+    Seed { sprouts_into: P, soil: P },
+    /// keeps `flame` burning above it
+    Candle { flame: P },
+}
+
+impl<P> Behaviour<P> {
+    /// pixels that can change without anything touching them keep their
+    /// chunk awake
+    pub fn is_restless(&self) -> bool {
+        !matches!(
+            self,
+            Behaviour::Static
+                | Behaviour::Powder
+                | Behaviour::Drift { .. }
+                | Behaviour::Soil { .. }
+                | Behaviour::Cover { .. }
+                | Behaviour::Liquid
+        )
+    }
+
+    fn resolve<Q>(self, mut f: impl FnMut(P) -> Result<Q, String>) -> Result<Behaviour<Q>, String> {
+        Ok(match self {
+            Behaviour::Static => Behaviour::Static,
+            Behaviour::Powder => Behaviour::Powder,
+            Behaviour::Drift { packs_into } => Behaviour::Drift { packs_into: f(packs_into)? },
+            Behaviour::Soil { grows_into } => Behaviour::Soil { grows_into: f(grows_into)? },
+            Behaviour::Cover { reverts_to } => Behaviour::Cover { reverts_to: f(reverts_to)? },
+            Behaviour::Liquid => Behaviour::Liquid,
+            Behaviour::Gas { fade } => Behaviour::Gas { fade: fade.clamp(0.0, 1.0) },
+            Behaviour::Fire { burns_out_to } => Behaviour::Fire { burns_out_to: f(burns_out_to)? },
+            Behaviour::Molten => Behaviour::Molten,
+            Behaviour::Plant { leaf } => Behaviour::Plant { leaf: f(leaf)? },
+            Behaviour::Seed { sprouts_into, soil } => Behaviour::Seed {
+                sprouts_into: f(sprouts_into)?,
+                soil: f(soil)?,
+            },
+            Behaviour::Candle { flame } => Behaviour::Candle { flame: f(flame)? },
+        })
+    }
+}
+
+/// the material it is listed under touching `with` turns into `into`, and
+/// `with` turns into `other_into`, at `chance` (0.0 - 1.0) per update. with
+/// a `catalyst` it only happens while the catalyst touches it too
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Reaction<P> {
+    pub with: P,
+    #[serde(default)]
+    pub catalyst: Option<P>,
+    pub chance: f32,
+    pub into: P,
+    pub other_into: P,
+}
+
+impl<P> Reaction<P> {
+    fn resolve<Q>(self, mut f: impl FnMut(P) -> Result<Q, String>) -> Result<Reaction<Q>, String> {
+        Ok(Reaction {
+            with: f(self.with)?,
+            catalyst: match self.catalyst {
+                Some(catalyst) => Some(f(catalyst)?),
+                None => None,
+            },
+            chance: self.chance.clamp(0.0, 1.0),
+            into: f(self.into)?,
+            other_into: f(self.other_into)?,
+        })
+    }
+}
+
+/// one entry of the materials file
+#[derive(Deserialize)]
+#[serde(default)]
+struct MaterialDef {
+    label: Option<String>,
+    color: (u8, u8, u8, u8),
+    darkness: f32,
+    light: Option<((u8, u8, u8), u8)>,
+    tint: Option<(f32, f32, f32)>,
+    density: Option<i32>,
+    airy: bool,
+    fluid: bool,
+    solid: bool,
+    ignition: f32,
+    heats_into: Vec<(String, f32)>,
+    explosion_strength: Option<f32>,
+    blast_resistance: f32,
+    hardness: u8,
+    player_damage: f32,
+    conductivity: f32,
+    heat: Option<(f32, f32)>,
+    when_hot: Option<(f32, String)>,
+    when_cold: Option<(f32, String)>,
+    fuel: u8,
+    wetness: u8,
+    viscosity: f32,
+    dispersion: u8,
+    windage: f32,
+    span: Option<u8>,
+    reactions: Vec<Reaction<String>>,
+    behaviour: Behaviour<String>,
+}
+
+impl Default for MaterialDef {
+    fn default() -> Self {
+        MaterialDef {
+            label: None,
+            color: (255, 0, 255, 255),
+            darkness: 1.0,
+            light: None,
+            tint: None,
+            density: None,
+            airy: false,
+            fluid: false,
+            solid: false,
+            ignition: 0.0,
+            heats_into: vec![],
+            explosion_strength: None,
+            blast_resistance: 1.0,
+            hardness: 0,
+            player_damage: 0.0,
+            conductivity: 0.1,
+            heat: None,
+            when_hot: None,
+            when_cold: None,
+            fuel: 0,
+            wetness: 0,
+            viscosity: 0.0,
+            dispersion: 1,
+            windage: 0.0,
+            span: None,
+            reactions: vec![],
+            behaviour: Behaviour::Static,
+        }
+    }
+}
+
+pub struct Material {
+    /// what the material is called in the file
+    pub name: String,
+    /// what the player sees
+    pub label: String,
+    /// rgba colour the pixel is drawn with
+    pub color: [u8; 4],
+    /// how much sky light fades going through the pixel, 0.0 not at all and
+    /// 1.0 the most. things that glow use `light`
+    pub darkness: f32,
+    /// colour (0.0 - 1.0 per channel) and radius in pixels of the light it
+    /// gives off
+    pub light: Option<([f32; 3], u8)>,
+    /// fraction of each colour of light that gets through it. light doesn't
+    /// go through things without one, it only lights up their surface
+    pub tint: Option<[f32; 3]>,
+    /// things with a density fall through things with less
+    pub density: Option<i32>,
+    pub airy: bool,
+    /// holds liquid, see `Map::fluid`
+    pub fluid: bool,
+    /// the player and entities collide with it
+    pub solid: bool,
+    /// percent chance of catching fire each time something tries
+    pub ignition: f32,
+    /// what it turns into when it catches fire. the first one whose
+    /// chance (0.0 - 1.0) comes up wins
+    pub heats_into: Vec<(Pixel, f32)>,
+    /// how hard it explodes when it catches fire. a blast loses 1 of
+    /// strength for every pixel it travels
+    pub explosion_strength: Option<f32>,
+    /// how much of a blast it takes to destroy it, and how much it takes out
+    /// of a blast going through it
+    pub blast_resistance: f32,
+    /// how good a pickaxe it takes to mine it
+    pub hardness: u8,
+    pub player_damage: f32,
+    /// how much of the temperature difference to each neighbour evens out
+    /// per update, 0.0 - 1.0. the lower of the two pixels is used
+    pub conductivity: f32,
+    /// a heat source pulls itself towards the temperature, making up that
+    /// fraction (0.0 - 1.0) of the difference per update
+    pub heat: Option<(f32, f32)>,
+    /// turns into the material once at least this hot
+    pub when_hot: Option<(f32, Pixel)>,
+    /// turns into the material once at most this hot
+    pub when_cold: Option<(f32, Pixel)>,
+    /// how many updates the fire it turns into keeps burning for
+    pub fuel: u8,
+    /// how much moisture it gives the soil around it, 0 - 255
+    pub wetness: u8,
+    /// chance (0.0 - 1.0) a liquid sits still instead of spreading, or a
+    /// powder instead of sliding, each update
+    pub viscosity: f32,
+    /// how many cells a liquid can spread sideways in one update
+    pub dispersion: u8,
+    /// how much the wind pushes it, 0.0 - 1.0
+    pub windage: f32,
+    /// for things that are built up, how far they can reach out sideways or
+    /// hang down from whatever holds them up before they break loose. solids
+    /// without one are the ground and hold up everything touching them
+    pub span: Option<u8>,
+    /// what it does to the things it touches, checked whenever it updates
+    pub reactions: Vec<Reaction<Pixel>>,
+    pub behaviour: Behaviour<Pixel>,
+}
+
+impl Material {
+    /// pixels that can change without anything touching them keep their
+    /// chunk awake
+    pub fn is_restless(&self) -> bool {
+        self.behaviour.is_restless() || self.heat.is_some()
+    }
+}
+
+pub struct Materials {
+    list: Vec<Material>,
+    by_name: HashMap<String, Pixel>,
+}
+
+impl Materials {
+    fn load() -> Materials {
+        if let Ok(text) = std::fs::read_to_string(MATERIALS_PATH) {
+            match Materials::parse(&text) {
+                Ok(materials) => return materials,
+                Err(error) => eprintln!("could not load {MATERIALS_PATH}, using the built in materials: {error}"),
+            }
+        }
+        Materials::parse(BUILT_IN_MATERIALS).expect("built in materials.ron is broken")
+    }
+
+    pub fn parse(text: &str) -> Result<Materials, String> {
+        let defs: HashMap<String, MaterialDef> = ron::from_str(text).map_err(|e| e.to_string())?;
+
+        // built in materials keep the ids `Pixel` has constants for, anything
+        // new goes after them in name order so the ids don't move around
+        let mut names: Vec<String> = Pixel::BUILT_IN.iter().map(|name| name.to_string()).collect();
+        if let Some(missing) = names.iter().find(|name| !defs.contains_key(*name)) {
+            return Err(format!("built in material {missing} is missing"));
+        }
+        let mut extra: Vec<String> = defs.keys().filter(|name| !names.contains(name)).cloned().collect();
+        extra.sort();
+        names.extend(extra);
+
+        if names.len() > u8::MAX as usize + 1 {
+            return Err(format!("too many materials ({}), the limit is 256", names.len()));
+        }
+
+        let by_name: HashMap<String, Pixel> = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.clone(), Pixel(id as u8)))
+            .collect();
+        let lookup = |name: String| {
+            by_name
+                .get(&name)
+                .copied()
+                .ok_or_else(|| format!("unknown material {name}"))
+        };
+
+        let phase_change = |change: Option<(f32, String)>| match change {
+            Some((temperature, into)) => Ok(Some((temperature, lookup(into)?))),
+            None => Ok(None),
+        };
+
+        let mut defs = defs;
+        let mut list = vec![];
+        for name in names {
+            let def = defs.remove(&name).unwrap();
+            let in_material = |error: String| format!("{name}: {error}");
+
+            // explosions are run from inside a chunk and can be set off by
+            // fire a little way off, so together they can't be bigger than
+            // the area a chunk is allowed to touch
+            let max_strength = MAX_REACH - IGNITE_REACH;
+            if def.explosion_strength.is_some_and(|strength| !(0.0..max_strength as f32).contains(&strength)) {
+                return Err(in_material(format!("explosion_strength has to be at least 0 and less than {max_strength}")));
+            }
+
+            if def.light.is_some_and(|(_, radius)| radius as usize > MAX_LIGHT_RADIUS) {
+                return Err(in_material(format!("light radius can't be more than {MAX_LIGHT_RADIUS}")));
+            }
+
+            // same goes for how far a liquid can spread in one go
+            if def.dispersion == 0 || def.dispersion as usize >= MAX_REACH {
+                return Err(in_material(format!("dispersion has to be between 1 and {}", MAX_REACH - 1)));
+            }
+
+            let (r, g, b, a) = def.color;
+            list.push(Material {
+                label: def.label.unwrap_or_else(|| name.clone()),
+                color: [r, g, b, a],
+                darkness: def.darkness,
+                light: def.light.map(|((r, g, b), radius)| ([r, g, b].map(|c| c as f32 / 255.0), radius)),
+                // air and other gases let light straight through unless they
+                // say otherwise
+                tint: def
+                    .tint
+                    .map(|(r, g, b)| [r, g, b].map(|c| c.clamp(0.0, 1.0)))
+                    .or(def.airy.then_some([1.0; 3])),
+                density: def.density,
+                airy: def.airy,
+                fluid: def.fluid,
+                solid: def.solid,
+                ignition: def.ignition,
+                heats_into: def
+                    .heats_into
+                    .into_iter()
+                    .map(|(product, chance)| Ok((lookup(product)?, chance)))
+                    .collect::<Result<_, String>>()
+                    .map_err(in_material)?,
+                explosion_strength: def.explosion_strength,
+                blast_resistance: def.blast_resistance.max(0.0),
+                hardness: def.hardness,
+                player_damage: def.player_damage,
+                conductivity: def.conductivity.clamp(0.0, 1.0),
+                heat: def.heat.map(|(temperature, rate)| (temperature, rate.clamp(0.0, 1.0))),
+                when_hot: phase_change(def.when_hot).map_err(in_material)?,
+                when_cold: phase_change(def.when_cold).map_err(in_material)?,
+                fuel: def.fuel,
+                wetness: def.wetness,
+                viscosity: def.viscosity.clamp(0.0, 1.0),
+                dispersion: def.dispersion,
+                windage: def.windage.clamp(0.0, 1.0),
+                span: def.span,
+                reactions: def
+                    .reactions
+                    .into_iter()
+                    .map(|reaction| reaction.resolve(lookup))
+                    .collect::<Result<_, String>>()
+                    .map_err(in_material)?,
+                behaviour: def.behaviour.resolve(lookup).map_err(in_material)?,
+                name,
+            });
+        }
+
+        Ok(Materials { list, by_name })
+    }
+
+    pub fn get(&self, pixel: Pixel) -> &Material {
+        &self.list[pixel.0 as usize]
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<Pixel> {
+        self.by_name.get(name).copied()
+    }
+
+    /// every material, built in ones first
+    pub fn pixels(&self) -> impl ExactSizeIterator<Item = Pixel> {
+        (0..self.list.len()).map(|id| Pixel(id as u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the built in materials with one more added on the end
+    fn with_extra(def: &str) -> Result<Materials, String> {
+        let end = BUILT_IN_MATERIALS.rfind('}').unwrap();
+        Materials::parse(&format!("{}    \"Extra\": ({def}),\n}}", &BUILT_IN_MATERIALS[..end]))
+    }
+
+    #[test]
+    fn built_in_materials_parse() {
+        assert!(Materials::parse(BUILT_IN_MATERIALS).is_ok());
+        assert!(with_extra("explosion_strength: Some(5.0), dispersion: 3, light: Some(((255, 255, 255), 16))").is_ok());
+    }
+
+    #[test]
+    fn rejects_explosions_bigger_than_a_chunk_can_reach() {
+        let max = MAX_REACH - IGNITE_REACH;
+        assert!(with_extra(&format!("explosion_strength: Some({max}.0)")).is_err());
+        assert!(with_extra("explosion_strength: Some(-1.0)").is_err());
+    }
+
+    #[test]
+    fn rejects_dispersion_out_of_range() {
+        assert!(with_extra("dispersion: 0").is_err());
+        assert!(with_extra(&format!("dispersion: {MAX_REACH}")).is_err());
+    }
+
+    #[test]
+    fn rejects_light_radius_out_of_range() {
+        assert!(with_extra(&format!("light: Some(((255, 255, 255), {}))", MAX_LIGHT_RADIUS + 1)).is_err());
+    }
+}
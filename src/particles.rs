@@ -0,0 +1,166 @@
+use crate::map::{Map, Pixel, PixelMeta};
+use crate::update::AMBIENT_TEMPERATURE;
+
+/// how much faster particles fall each update, in pixels per update
+const GRAVITY: f32 = 0.08;
+/// fraction of its speed a particle keeps each update
+const DRAG: f32 = 0.98;
+/// fraction of its sideways speed a particle keeps when it bounces off a wall
+const BOUNCE: f32 = 0.3;
+/// most particles flying at once, anything thrown past this is dropped
+const MAX_PARTICLES: usize = 4000;
+/// updates a spark or chip lasts if it doesn't hit anything first
+const SHORT_LIFE: u16 = 40;
+/// chips sprayed by mining one pixel
+const CHIPS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticleKind {
+    /// lands back in the grid as the pixel it was thrown as
+    Debris,
+    /// sets fire to whatever it hits
+    Spark,
+    /// only for show, gone when it hits something
+    Chip,
+}
+
+/// a pixel flying free of the grid. it falls, bounces off walls and lands
+/// when something stops it falling
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub pixel: Pixel,
+    pub kind: ParticleKind,
+    pub meta: PixelMeta,
+    /// how much liquid or gas it carries, see `Map::fluid`
+    pub fluid: f32,
+    pub temperature: f32,
+    /// position in pixels, x is the column and y the row
+    pub x: f32,
+    pub y: f32,
+    /// speed in pixels per update
+    pub vx: f32,
+    pub vy: f32,
+    /// updates since it was thrown
+    pub age: u16,
+}
+
+impl Particle {
+    /// a new particle from the middle of a cell
+    pub fn new(pixel: Pixel, kind: ParticleKind, row: usize, col: usize, vx: f32, vy: f32) -> Particle {
+        Particle {
+            pixel,
+            kind,
+            meta: PixelMeta::default().became(pixel),
+            fluid: if pixel.flows() { 1.0 } else { 0.0 },
+            temperature: AMBIENT_TEMPERATURE,
+            x: col as f32 + 0.5,
+            y: row as f32 + 0.5,
+            vx,
+            vy,
+            age: 0,
+        }
+    }
+}
+
+impl Map {
+    /// adds thrown particles, as long as there is room for them
+    pub fn add_particles(&mut self, particles: impl IntoIterator<Item = Particle>) {
+        let room = MAX_PARTICLES.saturating_sub(self.particles.len());
+        self.particles.extend(particles.into_iter().take(room));
+    }
+
+    /// sprays a few chips of a pixel that is being mined
+    pub fn spray_chips(&mut self, row: usize, col: usize) {
+        let pixel = self.grid[(row, col)];
+        let meta = self.meta[(row, col)];
+        let chips: Vec<Particle> = (0..CHIPS)
+            .map(|_| {
+                let vx = (self.rng.sim.f32() - 0.5) * 0.8;
+                let vy = -self.rng.sim.f32() * 0.6;
+                Particle {
+                    meta,
+                    ..Particle::new(pixel, ParticleKind::Chip, row, col, vx, vy)
+                }
+            })
+            .collect();
+        self.add_particles(chips);
+    }
+
+    /// moves every particle one update, landing the ones that stopped
+    pub fn update_particles(&mut self) {
+        let mut particles = std::mem::take(&mut self.particles);
+        particles.retain_mut(|particle| self.move_particle(particle));
+
+        // landing sparks can set off things that throw more particles
+        let thrown = std::mem::replace(&mut self.particles, particles);
+        self.add_particles(thrown);
+    }
+
+    /// returns whether the particle is still flying
+    fn move_particle(&mut self, particle: &mut Particle) -> bool {
+        particle.age = particle.age.saturating_add(1);
+        if particle.kind != ParticleKind::Debris && particle.age > SHORT_LIFE {
+            return false;
+        }
+
+        // something flowed or fell into it
+        let here = (particle.y as usize, particle.x as usize);
+        if !self.grid[here].is_airy() {
+            self.land(particle, here);
+            return false;
+        }
+
+        particle.vy += GRAVITY;
+        particle.vx *= DRAG;
+        particle.vy *= DRAG;
+
+        // a pixel at a time, so fast particles can't go through thin walls
+        let steps = particle.vx.abs().max(particle.vy.abs()).ceil().max(1.0);
+        let size = self.size as f32;
+        for _ in 0..steps as usize {
+            let x = particle.x + particle.vx / steps;
+            let y = particle.y + particle.vy / steps;
+            if x < 2.0 || y < 2.0 || x >= size - 2.0 || y >= size - 2.0 {
+                return false;
+            }
+
+            let (row, col) = (y as usize, x as usize);
+            if self.grid[(row, col)].is_airy() {
+                particle.x = x;
+                particle.y = y;
+            } else if row == particle.y as usize {
+                particle.vx = -particle.vx * BOUNCE;
+                break;
+            } else {
+                self.land(particle, (row, col));
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// what a particle does once it hits `hit` on its way down
+    fn land(&mut self, particle: &Particle, hit: (usize, usize)) {
+        match particle.kind {
+            ParticleKind::Debris => {
+                let col = particle.x as usize;
+                // something might have filled the spot while it was flying
+                let Some(row) = (0..8)
+                    .map_while(|up| (particle.y as usize).checked_sub(up))
+                    .find(|row| self.grid[(*row, col)].is_airy())
+                else {
+                    return;
+                };
+                self.set_px(row, col, particle.pixel);
+                self.meta[(row, col)] = particle.meta;
+                self.fluid[(row, col)] = particle.fluid;
+                self.temperature[(row, col)] = particle.temperature;
+            }
+            ParticleKind::Spark => {
+                self.ignite_px(hit.1 as i32, hit.0 as i32, false);
+            }
+            ParticleKind::Chip => {}
+        }
+    }
+}
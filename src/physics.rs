@@ -1,7 +1,7 @@
-use egui_macroquad::macroquad::{color::BLACK, math::{Vec2, Rect}, shapes::draw_line};
+use glam::Vec2;
 use grid::Grid;
 
-use crate::map::{Map, Pixel};
+use crate::map::Pixel;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CollisionDirection {
@@ -158,34 +158,6 @@ pub struct HitLineSet {
 }
 
 impl HitLineSet {
-    pub fn render(&self) {
-        for line in &self.horizontal {
-            let p1 = Vec2::new(line.x, line.y);
-            let p2 = Vec2::new(line.x + line.length, line.y);
-
-            draw_line(p1.x - 0.1, p1.y, p2.x + 0.1, p2.y, 0.2, BLACK);
-        }
-
-        // let points = self.vertical.par_iter().map(|line| {
-        //     (Vec2::new(line.x, line.y - 0.1),
-        //     Vec2::new(line.x, line.y + line.height + 0.1))
-        // }).chain(self.horizontal.par_iter().map(|line| {
-        //     (Vec2::new(line.x - 0.1, line.y),
-        //     Vec2::new(line.x + 0.1 + line.length, line.y))
-        // })).collect::<Vec<(Vec2, Vec2)>>();
-
-        // for (p1,p2) in points {
-        //     draw_line(p1.x, p1.y, p2.x, p2.y, 0.2, BLACK);
-        // }
-
-        for line in &self.vertical {
-            let p1 = Vec2::new(line.x, line.y);
-            let p2 = Vec2::new(line.x, line.y + line.height);
-
-            draw_line(p1.x, p1.y - 0.1, p2.x, p2.y + 0.1, 0.2, BLACK);
-        }
-    }
-
     pub fn get_collision_with(&self, other: &HitLineSet, v: Vec2) -> Option<Collision> {
         let mut res: Option<Collision> = None;
 
@@ -213,7 +185,9 @@ impl HitLineSet {
     }
 }
 
-pub fn make_map_box(grid: &Grid<Pixel>, view: Rect, waffle: bool, x: f32, y: f32) -> HitLineSet {
+/// the edges of every solid pixel in `view` (x, y, w, h) and of the map
+pub fn make_map_box(grid: &Grid<Pixel>, view: (f32, f32, f32, f32), waffle: bool, x: f32, y: f32) -> HitLineSet {
+    let (view_x, view_y, view_w, view_h) = view;
     let mut res = HitLineSet {
         vertical: vec![],
         horizontal: vec![],
@@ -239,11 +213,11 @@ pub fn make_map_box(grid: &Grid<Pixel>, view: Rect, waffle: bool, x: f32, y: f32
         true,
     ));
 
-    for row in 0.max((view.y - 2.0) as i32) as usize
-        ..(size as u32).min((view.y + view.h + 2.0) as u32) as usize
+    for row in 0.max((view_y - 2.0) as i32) as usize
+        ..(size as u32).min((view_y + view_h + 2.0) as u32) as usize
     {
-        for col in 0.max((view.x - 2.0) as i32) as usize
-            ..(size as u32).min((view.x + view.w + 2.0) as u32) as usize
+        for col in 0.max((view_x - 2.0) as i32) as usize
+            ..(size as u32).min((view_x + view_w + 2.0) as u32) as usize
         {
             if !grid[(row, col)].can_hit() {
                 continue;
@@ -314,15 +288,17 @@ pub fn make_map_box(grid: &Grid<Pixel>, view: Rect, waffle: bool, x: f32, y: f32
     res
 }
 
-pub fn make_bounding_box(rect: Rect) -> HitLineSet {
+/// the edges of a box (x, y, w, h)
+pub fn make_bounding_box(rect: (f32, f32, f32, f32)) -> HitLineSet {
+    let (x, y, w, h) = rect;
     HitLineSet {
         vertical: vec![
-            VerticalLine::new(rect.x, rect.y, rect.h, true),
-            VerticalLine::new(rect.x + rect.w, rect.y, rect.h, false),
+            VerticalLine::new(x, y, h, true),
+            VerticalLine::new(x + w, y, h, false),
         ],
         horizontal: vec![
-            HorizontalLine::new(rect.x, rect.y, rect.w, true),
-            HorizontalLine::new(rect.x, rect.y + rect.h, rect.w, false),
+            HorizontalLine::new(x, y, w, true),
+            HorizontalLine::new(x, y + h, w, false),
         ],
     }
 }
\ No newline at end of file
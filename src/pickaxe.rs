@@ -0,0 +1,78 @@
+use crate::map::Pixel;
+
+/// the pickaxes the player upgrades through, in order: name, the hardest
+/// material it can mine (see `Material::hardness`) and the ingots it takes
+/// to upgrade to it. every ingot has to be soft enough for the pickaxe
+/// before it to dig out, or the upgrade can never be paid for
+pub const PICKAXES: [(&str, u8, Option<(Pixel, i32)>); 3] = [
+    ("Stone Pickaxe", 1, None),
+    ("Copper Pickaxe", 2, Some((Pixel::Copper, 10))),
+    ("Iron Pickaxe", 3, Some((Pixel::Iron, 10))),
+];
+
+/// whether pickaxe `tier` (an index into `PICKAXES`) is good enough to mine
+/// a pixel
+pub fn can_mine(tier: u8, pixel: Pixel) -> bool {
+    pixel != Pixel::Air && pixel.material().hardness <= PICKAXES[tier as usize].1
+}
+
+/// the pickaxe after `tier` as (name, ingot, cost), if there is a better one
+pub fn next(tier: u8) -> Option<(&'static str, Pixel, i32)> {
+    let (name, _, cost) = PICKAXES.get(tier as usize + 1)?;
+    cost.map(|(ingot, count)| (*name, ingot, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::materials::MATERIALS;
+    use crate::update::SimFocus;
+
+    const SIZE: usize = 32;
+
+    /// heats a single pixel of `ore` past its melting point and lets the
+    /// simulation turn it into whatever it smelts into
+    fn smelt(ore: Pixel) -> Pixel {
+        let (limit, _) = ore.material().when_hot.expect("ore doesn't smelt");
+        let mut map = Map::new_seeded(SIZE, "smelt".into(), 1);
+        map.settings.sim_distance = SIZE as i32;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                map.grid[(row, col)] = Pixel::Bedrock;
+            }
+        }
+        map.reset_temperature();
+        map.reset_meta();
+        map.reset_fluid();
+        map.reset_stress();
+        map.set_px(16, 16, ore);
+        map.temperature[(16, 16)] = limit + 100.0;
+
+        let focus = SimFocus {
+            x: SIZE as f32 / 2.0,
+            y: SIZE as f32 / 2.0,
+            view: (0.0, 0.0, SIZE as f32, SIZE as f32),
+        };
+        for _ in 0..10 {
+            map.update_state(&focus);
+        }
+        map.grid[(16, 16)]
+    }
+
+    #[test]
+    fn every_pickaxe_can_be_reached() {
+        let mut tier = 0;
+        while let Some((name, ingot, _)) = next(tier) {
+            let ore = MATERIALS
+                .pixels()
+                .find(|px| matches!(px.material().when_hot, Some((_, into)) if into == ingot))
+                .unwrap_or_else(|| panic!("nothing smelts into the ingots for the {name}"));
+            assert!(can_mine(tier, ore), "{} can't mine {ore:?}", PICKAXES[tier as usize].0);
+            assert_eq!(smelt(ore), ingot, "{ore:?} didn't smelt into {ingot:?}");
+            assert!(can_mine(tier, ingot), "{} can't mine {ingot:?}", PICKAXES[tier as usize].0);
+            tier += 1;
+        }
+        assert_eq!(tier as usize, PICKAXES.len() - 1, "a pickaxe has no upgrade cost");
+    }
+}
@@ -2,7 +2,6 @@ use core::fmt;
 use savefile::{load_file, save_file};
 use savefile_derive::Savefile;
 use std::{fmt::Display, fs::create_dir_all};
-use strum::IntoEnumIterator;
 
 use egui_macroquad::macroquad::{
     camera::Camera2D,
@@ -14,8 +13,12 @@ use egui_macroquad::macroquad::{
     window::{screen_height, screen_width},
 };
 
-use crate::{map::Map, physics::{self, CollisionDirection, HitLineSet}, settings::Settings, SAVEFILE_VERSION};
-use crate::{craft::craft, map::Pixel};
+use crate::{map::Map, pickaxe::{self, PICKAXES}, physics::{self, CollisionDirection, HitLineSet}, settings::Settings, update::SimFocus, SAVEFILE_VERSION};
+use crate::{craft::craft, map::{Pixel, PixelMeta}};
+
+/// fraction of the player's walking speed, jump and falling speed left while
+/// in or on mud
+const MUD_DRAG: f32 = 0.5;
 
 #[derive(PartialEq, Debug, Clone, Savefile)]
 pub enum Item {
@@ -48,30 +51,7 @@ impl Display for Item {
             // ),
             Item::PlacePixel { pixel, count } => format!(
                 "{}x{}",
-                match pixel {
-                    Pixel::Air => "Air",
-                    Pixel::Sand => "Sand",
-                    Pixel::Dirt => "Dirt",
-                    Pixel::Stone => "Stone",
-                    Pixel::Water => "Water",
-                    Pixel::Candle => "Candle",
-                    Pixel::Fire => "Fire",
-                    Pixel::Grass => "Grass",
-                    Pixel::Wood => "Wood",
-                    Pixel::Bedrock => "Bedrock",
-                    Pixel::Smoke => "Smoke",
-                    Pixel::Steam => "Steam",
-                    Pixel::Gold => "Gold",
-                    Pixel::Oil => "Oil",
-                    Pixel::Glass => "Glass",
-                    Pixel::Lava => "Lava",
-                    Pixel::Explosive => "Explosive",
-                    Pixel::LiveWood => "Living Wood",
-                    Pixel::Seed => "Seed",
-                    Pixel::Leaf => "Leaf",
-                    Pixel::Loot => "Loot Box",
-                    Pixel::Lamp => "Lamp",
-                },
+                pixel.material().label,
                 count
             ),
         };
@@ -100,7 +80,7 @@ impl Default for Inventory {
 
 impl Inventory {
     pub fn creative() -> Self {
-        let mut items: Vec<Item> = Pixel::iter()
+        let mut items: Vec<Item> = Pixel::all()
             .map(|x| Item::PlacePixel {
                 pixel: x,
                 count: 9999999,
@@ -160,6 +140,9 @@ pub struct Player {
     #[savefile_default_val="100.0"]
     pub battery: f32,
     pub charging: bool,
+    /// which of `PICKAXES` the player has
+    #[savefile_versions = "9.."]
+    pub pickaxe: u8,
 }
 
 impl Default for Player {
@@ -182,6 +165,7 @@ impl Default for Player {
             battery: 100.0,
             charging: false,
             settings_open: false,
+            pickaxe: 0,
 
         }
     }
@@ -237,19 +221,7 @@ impl Player {
             //             .insert(0, Item::SpawnEntity { entity, count })
             //     }
             // }
-            Item::PlacePixel {
-                mut pixel,
-                mut count,
-            } => {
-                match pixel {
-                    Pixel::LiveWood => pixel = Pixel::Wood,
-                    Pixel::Loot => {
-                        pixel = fastrand::choice(Pixel::iter().collect::<Vec<Pixel>>())
-                            .unwrap_or(Pixel::Gold);
-                        count = fastrand::i32(10..200);
-                    }
-                    _ => {}
-                }
+            Item::PlacePixel { pixel, count } => {
                 let mut added_count = false;
                 for i in self.inventory.items.iter_mut() {
                     if let Item::PlacePixel {
@@ -272,6 +244,45 @@ impl Player {
         }
     }
 
+    pub fn pickaxe_name(&self) -> &'static str {
+        PICKAXES[self.pickaxe as usize].0
+    }
+
+    /// whether the player's pickaxe is good enough to mine a pixel
+    pub fn can_mine(&self, pixel: Pixel) -> bool {
+        pickaxe::can_mine(self.pickaxe, pixel)
+    }
+
+    /// the next pickaxe up as (name, ingot, cost), if there is a better one
+    pub fn next_pickaxe(&self) -> Option<(&'static str, Pixel, i32)> {
+        pickaxe::next(self.pickaxe)
+    }
+
+    /// trades ingots from the inventory (or hand) for the next pickaxe up.
+    /// returns whether the player had enough
+    pub fn upgrade_pickaxe(&mut self) -> bool {
+        let Some((_, ingot, cost)) = self.next_pickaxe() else {
+            return false;
+        };
+        let Some(count) = std::iter::once(&mut self.item_in_hand)
+            .chain(self.inventory.items.iter_mut())
+            .find_map(|item| match item {
+                Item::PlacePixel { pixel, count } if *pixel == ingot && *count >= cost => Some(count),
+                _ => None,
+            })
+        else {
+            return false;
+        };
+
+        *count -= cost;
+        if matches!(self.item_in_hand, Item::PlacePixel { count: 0, .. }) {
+            self.item_in_hand = Item::Hand;
+        }
+        self.inventory.items.retain(|item| !matches!(item, Item::PlacePixel { count: 0, .. }));
+        self.pickaxe += 1;
+        true
+    }
+
     pub fn craft_rect(&self, size: usize) -> Option<Rect> {
         match self.item_in_hand {
             Item::Crafter { start: Some(start) } => {
@@ -304,17 +315,17 @@ impl Player {
             let wand_rect = self
                 .craft_rect(map.size.clone() as usize)
                 .unwrap_or_default();
-            let result = craft(map.get_region(wand_rect));
+            let result = craft(map.get_region(wand_rect.x, wand_rect.y, wand_rect.w, wand_rect.h));
             if result.0 {
                 for ((row, col), i) in result.2.indexed_iter() {
                     let px = (row + wand_rect.y as usize, col + wand_rect.x as usize);
-                    map.grid[px] = *i;
-                    map.update_texture_px.insert(px);
+                    map.set_px(px.0, px.1, *i);
                 }
             }
         }
 
         let pos = (row, col);
+        let can_mine = self.can_mine(map.grid[pos]);
         match &mut self.item_in_hand {
             Item::Hand => {}
             Item::Crafter { start: Some(_) } => {
@@ -340,12 +351,11 @@ impl Player {
                 self.craft_timer = 0.2;
             }
             Item::Pickaxe => {
-                if map.grid[pos] != Pixel::Air {
-                    self.gain_item(Item::PlacePixel {
-                        pixel: map.grid[pos],
-                        count: 1,
-                    });
-                    map.grid[pos] = Pixel::Air;
+                if can_mine {
+                    let (pixel, count) = map.mined_drop(row, col);
+                    self.gain_item(Item::PlacePixel { pixel, count });
+                    map.spray_chips(row, col);
+                    map.set_px(row, col, Pixel::Air);
                 }
             }
             // Item::SpawnEntity { entity, count } => {
@@ -359,7 +369,8 @@ impl Player {
             Item::PlacePixel { pixel, count } => {
                 if map.grid[pos] != *pixel {
                     *count -= 1;
-                    map.grid[pos] = *pixel
+                    map.set_px(row, col, *pixel);
+                    map.meta[pos].flags |= PixelMeta::PLACED;
                 }
                 if *count == 0 {
                     self.item_in_hand = Item::Hand;
@@ -395,14 +406,48 @@ impl Player {
         self.battery = 100.0;
     }
 
+    /// knocks the player away from explosions, hurting them by however much
+    /// of each blast reaches them
+    pub fn feel_blasts(&mut self, map: &Map) {
+        /// health lost per point of blast power that reaches the player
+        const BLAST_DAMAGE: f32 = 2.0;
+        /// speed given per point of blast power
+        const BLAST_PUSH: f32 = 15.0;
+
+        let rect = self.rect();
+        let (row, col) = (rect.y + rect.h / 2.0, rect.x + rect.w / 2.0);
+        for blast in &map.blasts {
+            let power = blast.power_at(row, col, |pos| map.grid.get(pos.0, pos.1).copied().unwrap_or(Pixel::Bedrock));
+            if power > 0.0 {
+                let (x, y) = blast.direction(row, col);
+                self.vx += x * power * BLAST_PUSH;
+                self.vy += y * power * BLAST_PUSH;
+                self.health -= power * BLAST_DAMAGE;
+            }
+        }
+
+        if self.health < 0.0 {
+            self.respawn()
+        }
+    }
+
     pub fn get_player_box(&self, offset_x: f32, offset_y: f32) -> HitLineSet {
-        physics::make_bounding_box(Rect::new(self.x + offset_x, self.y + offset_y, 1.95, 2.95))
+        physics::make_bounding_box((self.x + offset_x, self.y + offset_y, 1.95, 2.95))
     }
 
     pub fn rect(&self) -> Rect {
         Rect::new(self.x, self.y, 1.95, 2.95)
     }
 
+    pub fn sim_focus(&self) -> SimFocus {
+        let view = self.view_port_cache;
+        SimFocus {
+            x: self.x,
+            y: self.y,
+            view: (view.x, view.y, view.w, view.h),
+        }
+    }
+
     pub fn update(&mut self, map: &Map, settings: &Settings) {
         let delta = if is_key_down(KeyCode::K) && cfg!(debug_assertions) {
             get_frame_time() * 10.0
@@ -426,7 +471,8 @@ impl Player {
 
         let mut damage: f32 = 0.0;
 
-        for pixel in map.get_region(self.rect()).iter() {
+        let rect = self.rect();
+        for pixel in map.get_region(rect.x, rect.y, rect.w, rect.h).iter() {
             damage = damage.max(pixel.player_damage());
         }
 
@@ -438,7 +484,7 @@ impl Player {
 
         let terrain_hit = physics::make_map_box(
             &map.grid, 
-            Rect::new(self.x - 20.0, self.y - 20.0, 40.0, 40.0), 
+            (self.x - 20.0, self.y - 20.0, 40.0, 40.0), 
             true, 
             self.x, 
             self.y
@@ -501,7 +547,8 @@ impl Player {
             }
         }
 
-        let region = map.get_region(self.rect());
+        let rect = self.rect();
+        let region = map.get_region(rect.x, rect.y, rect.w, rect.h);
         let mut in_water = false;
 
         for pixel in region.iter() {
@@ -510,7 +557,12 @@ impl Player {
             }
         }
 
-        let max_falling_speed = if in_water { 10.0 } else { 40.0 };
+        // standing on mud counts as well as being in it
+        let feet = map.get_region(rect.x, rect.y + rect.h, rect.w, 1.0);
+        let in_mud = region.iter().chain(feet.iter()).any(|pixel| *pixel == Pixel::Mud);
+        let grip = if in_mud { MUD_DRAG } else { 1.0 };
+
+        let max_falling_speed = (if in_water { 10.0 } else { 40.0 }) * grip;
 
         self.vy += if self.vy > max_falling_speed {
             0.0
@@ -518,10 +570,13 @@ impl Player {
             max_falling_speed * delta * 12.0
         };
 
-        if map.sky_light[self.x as usize] >= self.y as usize
-            || map.sky_light[self.x as usize + 1] >= self.y as usize
+        // solar charging, so only while the sun is up
+        let daylight = map.clock.daylight();
+        if (map.in_sunlight(self.y as usize, self.x as usize)
+            || map.in_sunlight(self.y as usize, self.x as usize + 1))
+            && daylight > 0.0
         {
-            self.battery += delta;
+            self.battery += delta * daylight;
             self.charging = true;
         } else {
             self.charging = false;
@@ -533,12 +588,12 @@ impl Player {
         self.craft_timer = self.craft_timer.clamp(0.0, 1.0);
 
         if (on_ground | in_water) && is_key_down(KeyCode::Space) && self.vy > -100.0 {
-            self.vy -= if in_water { 10.0 } else { 50.0 };
+            self.vy -= (if in_water { 10.0 } else { 50.0 }) * grip;
             self.jump_height_timer = 0.2;
         }
 
         if jump_pressed && self.vy > -200.0 && self.jump_height_timer > 0.0 {
-            self.vy -= 500.0 * delta;
+            self.vy -= 500.0 * delta * grip;
         }
         self.battery -= delta * 0.1;
 
@@ -556,14 +611,14 @@ impl Player {
         }
 
         if move_left_pressed && self.vx > -500.0 {
-            self.vx -= 8.0;
+            self.vx -= 8.0 * grip;
         }
         // if is_key_down(KeyCode::A) && self.vx > -500.0 && on_ground {
         //     self.vx -= 4.0;
         // }
 
         if move_right_pressed && self.vx < 500.0 {
-            self.vx += 8.0;
+            self.vx += 8.0 * grip;
         }
         // if is_key_down(KeyCode::D) && self.vx < 500.0 && on_ground {
         //     self.vx +=8.0;
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use egui_macroquad::macroquad::{
+    color::{Color, BLACK, WHITE},
+    miniquad::FilterMode,
+    shapes::draw_line,
+    texture::{Image, Texture2D},
+    time::get_time,
+};
+
+use crate::{
+    entity::{Entity, EntityType},
+    map::{Map, Pixel, PixelMeta},
+    physics::HitLineSet,
+};
+
+/// how far `color_seed` moves a pixel's brightness either way
+const COLOR_VARIATION: f32 = 0.06;
+/// how much darker fully soaked soil is drawn
+const WET_DARKENING: f32 = 0.35;
+/// seconds between new colours for flickering pixels
+const FLICKER_INTERVAL: f64 = 0.1;
+
+/// bedrock shimmers. it never changes in the simulation, so the renderer
+/// gives it new colours on a timer instead of the map marking it dirty
+fn flickers(pixel: Pixel) -> bool {
+    pixel == Pixel::Bedrock
+}
+
+pub fn pixel_color(pixel: Pixel, meta: PixelMeta) -> Color {
+    // flickering pixels get a new colour every time they are drawn. this
+    // only affects drawing so it's fine to use the global rng here rather
+    // than the map's
+    if flickers(pixel) {
+        return Color::from_rgba(fastrand::u8(0..255), fastrand::u8(0..255), fastrand::u8(0..255), 255);
+    }
+    let [r, g, b, a] = pixel.color();
+    let shade = 1.0 + (meta.color_seed as f32 / 255.0 * 2.0 - 1.0) * COLOR_VARIATION;
+    let shade = shade * (1.0 - meta.moisture as f32 / 255.0 * WET_DARKENING);
+    let mut color = Color::from_rgba(r, g, b, a);
+    color.r = (color.r * shade).min(1.0);
+    color.g = (color.g * shade).min(1.0);
+    color.b = (color.b * shade).min(1.0);
+    color
+}
+
+/// the light reaching a cell as the light texture stores it. rgb is the
+/// light itself, which the light shader multiplies the world by. alpha is
+/// how dark it is, for when there's no shader and it is drawn on top
+pub fn light_color(map: &Map, row: usize, col: usize) -> Color {
+    let [r, g, b] = map.light_at(row, col);
+    Color { r, g, b, a: 1.0 - r.max(g).max(b) }
+}
+
+/// keeps the gpu side of a map in sync with the simulation by redrawing
+/// only the pixels the map has marked as changed
+pub struct MapRenderer {
+    pub image: Image,
+    pub light_image: Image,
+    pub texture: Texture2D,
+    pub light_texture: Texture2D,
+    entity_textures: HashMap<(&'static str, usize), Texture2D>,
+    /// every pixel that `flickers`, and when they last got new colours
+    flickering: HashSet<(usize, usize)>,
+    flickered_at: f64,
+}
+
+impl MapRenderer {
+    pub fn new(map: &Map) -> MapRenderer {
+        let size = map.size as u16;
+        let mut image = Image::gen_image_color(size, size, WHITE);
+        let mut light_image = Image::gen_image_color(size, size, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.3 });
+
+        let mut flickering = HashSet::new();
+        for ((row, col), pixel) in map.grid.indexed_iter() {
+            image.set_pixel(col as u32, row as u32, pixel_color(*pixel, map.meta[(row, col)]));
+            light_image.set_pixel(col as u32, row as u32, light_color(map, row, col));
+            if flickers(*pixel) {
+                flickering.insert((row, col));
+            }
+        }
+
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+        let light_texture = Texture2D::from_image(&light_image);
+
+        MapRenderer {
+            image,
+            light_image,
+            texture,
+            light_texture,
+            entity_textures: HashMap::default(),
+            flickering,
+            flickered_at: get_time(),
+        }
+    }
+
+    /// uploads everything that changed in the map since the last call
+    pub fn update(&mut self, map: &mut Map) {
+        let dirty = map.drain_dirty_px();
+        let mut changed = !dirty.is_empty();
+        for (row, col) in dirty {
+            let pixel = map.grid[(row, col)];
+            self.image.set_pixel(col as u32, row as u32, pixel_color(pixel, map.meta[(row, col)]));
+            if flickers(pixel) {
+                self.flickering.insert((row, col));
+            } else {
+                self.flickering.remove(&(row, col));
+            }
+        }
+
+        if get_time() - self.flickered_at >= FLICKER_INTERVAL {
+            self.flickered_at = get_time();
+            for (row, col) in &self.flickering {
+                self.image.set_pixel(*col as u32, *row as u32, pixel_color(map.grid[(*row, *col)], map.meta[(*row, *col)]));
+            }
+            changed |= !self.flickering.is_empty();
+        }
+
+        if changed {
+            self.texture.update(&self.image);
+        }
+
+        if !map.update_light_px.is_empty() {
+            for (row, col) in std::mem::take(&mut map.update_light_px) {
+                self.light_image.set_pixel(col as u32, row as u32, light_color(map, row, col));
+            }
+            self.light_texture.update(&self.light_image);
+        }
+    }
+
+    pub fn entity_texture(&mut self, entity: &Entity) -> Texture2D {
+        let sprites: &[&'static [u8]] = match entity.entity_type {
+            EntityType::Tree => &[
+                include_bytes!("textures/tree/tree1.png"),
+                include_bytes!("textures/tree/tree2.png"),
+            ],
+            EntityType::Soul => &[include_bytes!("textures/soul/soul1.png")],
+            EntityType::Fish { air: _ } => &[include_bytes!("textures/fish/fish1.png")],
+            EntityType::Boid => &[include_bytes!("textures/fish/fish1.png")],
+        };
+        let name = match entity.entity_type {
+            EntityType::Tree => "tree",
+            EntityType::Soul => "soul",
+            EntityType::Fish { air: _ } => "fish",
+            EntityType::Boid => "boid",
+        };
+
+        *self
+            .entity_textures
+            .entry((name, entity.variant))
+            .or_insert_with(|| {
+                let bytes = sprites
+                    .get(entity.variant)
+                    .copied()
+                    .unwrap_or(include_bytes!("textures/error.png"));
+                let texture = Texture2D::from_file_with_format(bytes, None);
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+    }
+}
+
+/// draws the edges of a set of hit lines, for seeing what the player bumps
+/// into
+pub fn draw_hit_lines(hit: &HitLineSet) {
+    for line in &hit.horizontal {
+        draw_line(line.x - 0.1, line.y, line.x + line.length + 0.1, line.y, 0.2, BLACK);
+    }
+    for line in &hit.vertical {
+        draw_line(line.x, line.y - 0.1, line.x, line.y + line.height + 0.1, 0.2, BLACK);
+    }
+}
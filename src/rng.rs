@@ -0,0 +1,43 @@
+use fastrand::Rng;
+
+/// every random number the simulation uses comes from one of these streams.
+///
+/// they are split up so that e.g. opening a loot box can't change how the
+/// water in the next cave flows. the same seed and the same inputs always
+/// give the same world.
+pub struct MapRng {
+    /// terrain generation, only used while a new map is made
+    pub worldgen: Rng,
+    /// pixel rules and entities
+    pub sim: Rng,
+    /// what loot boxes turn into
+    pub loot: Rng,
+}
+
+impl MapRng {
+    pub fn new(seed: u64) -> MapRng {
+        let mut root = Rng::with_seed(seed);
+        MapRng {
+            worldgen: root.fork(),
+            sim: root.fork(),
+            loot: root.fork(),
+        }
+    }
+
+    /// the current position of every stream, so a saved map carries on
+    /// exactly where it left off
+    pub fn state(&self) -> Vec<u64> {
+        vec![self.worldgen.get_seed(), self.sim.get_seed(), self.loot.get_seed()]
+    }
+
+    pub fn from_state(seed: u64, state: &[u64]) -> MapRng {
+        match state {
+            [worldgen, sim, loot] => MapRng {
+                worldgen: Rng::with_seed(*worldgen),
+                sim: Rng::with_seed(*sim),
+                loot: Rng::with_seed(*loot),
+            },
+            _ => MapRng::new(seed),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+use crate::map::{Map, Pixel, PixelMeta};
+
+/// days each season lasts
+pub const DAYS_PER_SEASON: u32 = 3;
+/// cells checked for freezing, thawing or dropping leaves per tick, for
+/// every this many cells being simulated
+const SEASON_SAMPLES: usize = 400;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// the season a day falls in. maps start in spring
+    pub fn of_day(day: u32) -> Season {
+        match (day / DAYS_PER_SEASON) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    /// how fast plants grow compared to summer
+    pub fn growth(self) -> f32 {
+        match self {
+            Season::Spring => 1.5,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.4,
+            Season::Winter => 0.0,
+        }
+    }
+}
+
+impl Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Autumn => "Autumn",
+            Season::Winter => "Winter",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Map {
+    /// changes the open parts of the map a bit at a time with the season:
+    /// leaves drop in autumn, water left out under the sky freezes over in
+    /// winter, and the ice and snow melt again in spring and summer. still
+    /// water is asleep, so this can't be done in `update_px`
+    pub fn update_season(&mut self, rows: RangeInclusive<usize>, cols: RangeInclusive<usize>) {
+        let season = self.clock.season();
+        let area = (rows.end() - rows.start() + 1) * (cols.end() - cols.start() + 1);
+
+        for _ in 0..area / SEASON_SAMPLES + 1 {
+            let row = self.rng.sim.usize(rows.clone());
+            let col = self.rng.sim.usize(cols.clone());
+            let px = self.grid[(row, col)];
+            let exposed = self.grid[(row - 1, col)].is_airy() && self.in_sunlight(row - 1, col);
+
+            match season {
+                Season::Autumn if px == Pixel::Leaf && self.grid[(row + 1, col)].is_airy() => {
+                    let meta = self.meta[(row, col)];
+                    self.meta[(row, col)] = PixelMeta { flags: meta.flags | PixelMeta::LOOSE, ..meta };
+                    self.chunks.wake(row, col);
+                }
+                Season::Winter if px == Pixel::Water && exposed => {
+                    self.set_px(row, col, Pixel::Ice);
+                }
+                Season::Spring | Season::Summer if (px == Pixel::Ice || px == Pixel::Snow) && exposed => {
+                    self.set_px(row, col, Pixel::Water);
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,8 +1,7 @@
-use egui_macroquad::{egui::{self, Align2, Button, RichText}, macroquad::prelude::*};
 use savefile::load_file;
 use savefile_derive::Savefile;
 
-use crate::SAVEFILE_VERSION;
+use crate::{clock::DEFAULT_DAY_LENGTH, SAVEFILE_VERSION};
 
 
 
@@ -18,6 +17,10 @@ pub struct Settings {
     pub min_fps: i32,
     pub dynamic_simulation_distance: bool,
     pub open: bool,
+    /// ticks from one midnight to the next
+    #[savefile_versions = "7.."]
+    #[savefile_default_val = "18000"]
+    pub day_length: u32,
 }
 
 impl Default for Settings {
@@ -31,6 +34,7 @@ impl Default for Settings {
             min_fps: 25,
             dynamic_simulation_distance: true,
             open: false,
+            day_length: DEFAULT_DAY_LENGTH,
         };
         
         if cfg!(not(target_family="wasm")) {
@@ -49,94 +53,4 @@ impl Settings {
     pub fn save(&self) {
         savefile::save_file("saves/user_settings.bin", SAVEFILE_VERSION, self);
     }
-
-
-
-
-    pub async fn settings_ui(&mut self, blur_material:  Option<Material>) {
-
-        let mut sim_distance_string: String = self.sim_distance.to_string();
-        let mut min_fps_string: String = self.min_fps.to_string();
-
-        // let mut text_edit_number = 0;
-
-        let background = get_screen_data();
-
-        // background.bytes = gaussian_blur::blur(background.height(), background.width(), [1, 14, 62, 102, 62, 14, 1], background.bytes).0;
-
-        let background_texture = Texture2D::from_image(&background);
-
-        while self.open{
-            clear_background(LIGHTGRAY);
-
-            if let Some(mat) = blur_material {
-            gl_use_material(mat);
-            mat.set_uniform("textureSize", (screen_width(), screen_height()));
-            draw_texture_ex(background_texture, 0.0, 0.0, WHITE, DrawTextureParams {
-                dest_size: Some(Vec2{y:screen_height(), x:screen_width()}),
-                source: None,
-                rotation: 0.0,
-                flip_x: false,
-                flip_y: true,
-                pivot: None,
-            });
-            
-            }
-    
-            egui_macroquad::ui(|egui_ctx| {
-                egui::Window::new("Settings")
-                    .anchor(Align2::LEFT_TOP, [0.0, 0.0])
-                    .scroll2([false,true])
-                    .collapsible(false)
-                    .fixed_size([screen_width(),screen_height()])
-                    .open(&mut self.open)
-                    .show(egui_ctx, |ui| {
-                        // ui.label();
-                        
-                        ui.vertical(|ui| {
-                        
-                        
-                        
-                        if ui
-                                .button(format!(
-                                    "[{}] Dynamic Simulation Distance",
-                                    if self.dynamic_simulation_distance { "x" } else { " " }
-                                ))
-                                .clicked()
-                            {
-                                self.dynamic_simulation_distance = !self.dynamic_simulation_distance
-                            };
-
-                        // edit block
-                        ui.horizontal(|ui| {
-                            ui.label("Dynamic simulation distance: [");
-                            if ui.text_edit_singleline(&mut sim_distance_string).lost_focus() {
-                                if let Ok(num) = sim_distance_string.parse::<i32>() {
-                                    self.sim_distance = num;
-                                }
-                                sim_distance_string = self.sim_distance.to_string();
-                            };
-                            ui.label("]")
-                        });
-                        
-                        ui.horizontal(|ui| {
-                            ui.label("Minimum FPS: [");
-                            if ui.text_edit_singleline(&mut min_fps_string).lost_focus() {
-                                if let Ok(num) = min_fps_string.parse::<i32>() {
-                                    self.min_fps = num;
-                                }
-                                min_fps_string = self.min_fps.to_string();
-                            };
-                            ui.label("]")
-                        });
-                    });
-
-                    });
-                });
-
-            egui_macroquad::draw();
-
-            next_frame().await;
-        }
-    }
-}
\ No newline at end of file
+}
@@ -31,7 +31,7 @@ void main() {
     vec4 c2 = texture(Texture, uv);
     c2.a = 1.0-c2.a;
 
-    color = vec4(0.0, 0.0, 0.0, 1.0) * (texture(Texture, uv) * 4.0 + texture(Texture, uv + o1) + texture(Texture, uv + o2) + texture(Texture, uv - o1) + texture(Texture, uv - o2)).a * 0.125;
+    color = vec4((texture(Texture, uv) * 4.0 + texture(Texture, uv + o1) + texture(Texture, uv + o2) + texture(Texture, uv - o1) + texture(Texture, uv - o2)).rgb * 0.125, 1.0);
     color = color;
     //color = texture(Texture, coord / textureSize);
 
@@ -0,0 +1,122 @@
+use std::f32::consts::TAU;
+use std::ops::RangeInclusive;
+
+use grid::Grid;
+
+use crate::clock::Clock;
+use crate::map::Pixel;
+
+/// updates between working out again where the sun is blocked
+const SUN_REFRESH: u64 = 20;
+/// most pixels sideways sunlight moves for every pixel down, so it still
+/// reaches the ground when the sun is on the horizon
+const MAX_SLOPE: f32 = 2.0;
+
+/// sunlight (or moonlight) falling across the map at an angle. it is split
+/// into parallel rays, each stopped by the first thing it can't go through
+pub struct Sun {
+    /// pixels sideways the light moves for every pixel down, negative is to
+    /// the left
+    pub slope: f32,
+    size: usize,
+    /// rays that start off the side of the map, so the corners are lit when
+    /// the light comes in at an angle
+    offset: usize,
+    /// the row each ray is stopped at, or the bottom of the map
+    stops: Vec<usize>,
+}
+
+impl Sun {
+    pub fn new(size: usize) -> Sun {
+        let offset = (size as f32 * MAX_SLOPE).ceil() as usize;
+        Sun {
+            slope: 0.0,
+            size,
+            offset,
+            stops: vec![size; size + offset * 2],
+        }
+    }
+
+    /// width of the map the sun is shining on
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// which way the light falls at a time of day. the sun rises on the
+    /// right and sets on the left, and the moon does the same at night
+    pub fn slope_at(clock: &Clock) -> f32 {
+        // how far the sun (or moon) is from straight up, -0.25 - 0.25 turns
+        let from_noon = (clock.time + 0.25).rem_euclid(0.5) - 0.25;
+        (from_noon * TAU).tan().clamp(-MAX_SLOPE, MAX_SLOPE)
+    }
+
+    /// whether the sky lights a pixel
+    pub fn lights(&self, row: usize, col: usize) -> bool {
+        self.stops.get(self.ray(row, col)).is_some_and(|stop| *stop > row)
+    }
+
+    /// moves the sun along with the clock, and every so often works out
+    /// again where each ray is stopped. returns whether it did
+    pub fn update(&mut self, grid: &Grid<Pixel>, clock: &Clock, tick: u64) -> bool {
+        if tick % SUN_REFRESH != 0 {
+            return false;
+        }
+        self.refresh(grid, clock);
+        true
+    }
+
+    /// follows every ray down from the top of the map until something stops it
+    pub fn refresh(&mut self, grid: &Grid<Pixel>, clock: &Clock) {
+        self.slope = Sun::slope_at(clock);
+        let (slope, size) = (self.slope, self.size as f32);
+        for (ray, stop) in self.stops.iter_mut().enumerate() {
+            let start = ray as f32 - self.offset as f32;
+            // only the rows where the ray is over the map need checking
+            let rows = if self.slope == 0.0 {
+                if (0.0..size).contains(&start) { 0.0..size } else { 0.0..0.0 }
+            } else {
+                let (a, b) = ((-0.5 - start) / self.slope, (size - 0.5 - start) / self.slope);
+                a.min(b).max(0.0)..a.max(b).min(size)
+            };
+            *stop = (rows.start.floor() as usize..rows.end.ceil() as usize)
+                .find(|row| {
+                    Sun::crossing(slope, start, *row).any(|col| {
+                        col >= 0 && (col as usize) < self.size && !grid[(*row, col as usize)].lets_light_through()
+                    })
+                })
+                .unwrap_or(self.size);
+        }
+    }
+
+    /// something that blocks light was put at `row`, `col`, so the rays going
+    /// through it stop there now
+    pub fn block(&mut self, row: usize, col: usize) {
+        let ray = self.ray(row, col);
+        let reach = self.slope.abs().ceil() as usize;
+        for ray in ray.saturating_sub(reach)..=ray + reach {
+            let start = ray as f32 - self.offset as f32;
+            if !Sun::crossing(self.slope, start, row).contains(&(col as i64)) {
+                continue;
+            }
+            if let Some(stop) = self.stops.get_mut(ray) {
+                *stop = (*stop).min(row);
+            }
+        }
+    }
+
+    /// the columns a ray crosses in a row. when the light comes in flatter
+    /// than 45 degrees it moves more than one column for every row down, and
+    /// all the columns it passes over since the row above are counted here
+    /// so thin walls still cast a shadow
+    fn crossing(slope: f32, start: f32, row: usize) -> RangeInclusive<i64> {
+        let col = (start + slope * row as f32).round() as i64;
+        let above = (start + slope * (row as f32 - 1.0)).round() as i64;
+        let first = if above == col { col } else { above + (col - above).signum() };
+        first.min(col)..=first.max(col)
+    }
+
+    /// the ray that goes through a pixel
+    fn ray(&self, row: usize, col: usize) -> usize {
+        (col as f32 - self.slope * row as f32 + self.offset as f32).round().max(0.0) as usize
+    }
+}
@@ -1,92 +1,258 @@
+use std::ops::RangeInclusive;
+
 use crate::{
-    entity::EntityType,
-    map::{Biome, Map, Pixel},
-    player::Player,
+    chunk::{Chunks, DirtyRect, SharedGrid, CHUNK_SIZE, IGNITE_REACH},
+    entity::{BoidData, EntityType},
+    explosion::Blast,
+    map::{Biome, Map, Pixel, PixelMeta},
+    materials::Behaviour,
+    particles::{Particle, ParticleKind},
     settings::{FPS_BUFFER, MIN_SIM_DISTANCE},
+    sun::Sun,
+    wind::Wind,
 };
-use egui_macroquad::macroquad::{color::Color, math::Vec2};
+use fastrand::Rng;
 use grid::Grid;
-use macroquad::time::{get_fps, get_frame_time};
 use rayon::prelude::*;
 
-impl Map {
-    pub fn update_state(&mut self, player: &Player) {
-        self.block_percent.clear();
+/// temperature everything cools down (or warms up) to
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+/// fraction of the difference to `AMBIENT_TEMPERATURE` lost per update
+const HEAT_LOSS: f32 = 0.005;
+/// a pixel whose temperature moved less than this is left to sleep
+const SETTLED_TEMPERATURE: f32 = 0.05;
+/// moisture lost each pixel it soaks through soil
+const SOAK_LOSS: u8 = 24;
+/// how much more a cell of liquid holds for each cell of the same liquid
+/// above it. the extra is what pushes liquid up the far side of a u-bend
+pub const FLUID_COMPRESSION: f32 = 0.05;
+/// a cell holding less liquid than this empties out
+const MIN_FLUID: f32 = 0.005;
+/// flows smaller than this are skipped so still liquid can fall asleep
+const MIN_FLOW: f32 = 0.0001;
+/// pressure it takes to push a new pixel of liquid up into the air, about
+/// half a cell of head
+const MIN_PUSH: f32 = FLUID_COMPRESSION / 2.0;
+/// a cell holding less gas than this thins out into air, and gas doesn't
+/// spread into the air next to it if it would leave less than this there
+const MIN_GAS: f32 = 0.05;
+/// wind weaker than this doesn't wake anything up to be blown
+const CALM_WIND: f32 = 0.05;
+/// the smallest body of water fish turn up in
+const FISH_WATER: usize = 40;
+/// something that was just set alight (or boiled by fire) is at least this hot
+const FLASH_TEMPERATURE: f32 = 150.0;
+/// darkness of the open sky in the middle of the day
+const SKY_DARKNESS: f32 = 0.1;
+/// how much darker the open sky is at night
+const NIGHT_DARKNESS: f32 = 0.75;
+/// chance liquid falling onto the same liquid throws up a drop
+const SPLASH_CHANCE: f32 = 0.05;
+/// chance something an explosion destroys but doesn't burn gets thrown
+/// instead of vanishing
+const DEBRIS_CHANCE: f32 = 0.5;
+/// sparks thrown out of every explosion
+const SPARKS: usize = 6;
+/// pixels of a drift that have to be piled on top for it to pack down
+const PACK_DEPTH: i32 = 4;
+/// chance a drift under a deep enough pile packs down per update
+const PACK_CHANCE: f32 = 0.01;
+/// anything this cold or colder won't catch fire
+const FROZEN_TEMPERATURE: f32 = 0.0;
+
+/// the spot the simulation is centred on, normally the player
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimFocus {
+    pub x: f32,
+    pub y: f32,
+    /// (x, y, w, h) of the area that is on screen
+    pub view: (f32, f32, f32, f32),
+}
+
+impl SimFocus {
+    pub fn in_view(&self, col: f32, row: f32) -> bool {
+        let (x, y, w, h) = self.view;
+        col >= x && col <= x + w && row >= y && row <= y + h
+    }
+}
 
-        // change simulation distance based on fps
+impl Map {
+    /// change simulation distance based on fps
+    pub fn adjust_sim_distance(&mut self, fps: i32) {
         if self.settings.dynamic_simulation_distance {
-            if get_fps() < self.settings.min_fps && self.settings.sim_distance > MIN_SIM_DISTANCE {
+            if fps < self.settings.min_fps && self.settings.sim_distance > MIN_SIM_DISTANCE {
                 self.settings.sim_distance =
                     (self.settings.sim_distance - 1).clamp(MIN_SIM_DISTANCE, self.size as i32);
                 
-            }else if get_fps() > self.settings.min_fps + FPS_BUFFER && self.settings.sim_distance < self.size as i32 + 20  {
+            }else if fps > self.settings.min_fps + FPS_BUFFER && self.settings.sim_distance < self.size as i32 + 20  {
                 self.settings.sim_distance = self.settings.sim_distance + 1;
             }
         }
-        
+    }
 
-        let pts = if self.settings.sim_distance < self.size as i32 {
-            [
-                (
-                    0..
-                    (0.5 * ((self.settings.sim_distance*2).pow(2)) as f32) as usize
-        )
-                .map(|_| {
-                    (
-                        (fastrand::i32(
-                            (player.x as i32 - self.settings.sim_distance).max(2)
-                                ..(self.settings.sim_distance as i32 + player.x as i32)
-                                    .min(self.size as i32 - 2),
-                        )),
-                        (fastrand::i32(
-                            (player.y as i32 - self.settings.sim_distance).max(2)
-                                ..(self.settings.sim_distance as i32 + player.y as i32)
-                                    .min(self.size as i32 - 2),
-                        )),
-                    )
-                })
-                .collect::<Vec<(i32, i32)>>(),
-                (0..self.settings.sim_distance as usize)
-                    .map(|_| {
-                        (
-                            fastrand::i32(2..self.size as i32 - 2),
-                            fastrand::i32(2..self.size as i32 - 2),
-                        )
-                    })
-                    .collect::<Vec<(i32, i32)>>(),
-            ]
-            .concat()
-        } else {
-            (0..(0.5 * (self.size as f32).powi(2)) as usize)
-                .map(|_| {
-                    (
-                        fastrand::i32(2..self.size as i32 - 2),
-                        fastrand::i32(2..self.size as i32 - 2),
-                    )
-                })
-                .collect::<Vec<(i32, i32)>>()
+    pub fn update_state(&mut self, focus: &SimFocus) {
+        self.block_percent.clear();
+        self.blasts.clear();
+        // whoever draws the map takes these before every update, and nobody
+        // else needs them kept
+        self.update_texture_px.clear();
+        self.tick += 1;
+        self.clock.tick(self.settings.day_length);
+        self.burn_fuses();
+
+        let size = self.size as usize;
+        let (first_row, last_row) = self.sim_range(focus.y);
+        let (first_col, last_col) = self.sim_range(focus.x);
+        self.sun.update(&self.grid, &self.clock, self.tick);
+        if self.wind.update(&self.grid, self.tick, &mut self.rng.sim) {
+            self.wake_blown(first_row..=last_row, first_col..=last_col);
+        }
+        self.update_weather(first_row..=last_row, first_col..=last_col);
+        self.update_season(first_row..=last_row, first_col..=last_col);
+
+        self.chunks.begin_tick(
+            (first_row / CHUNK_SIZE, last_row / CHUNK_SIZE),
+            (first_col / CHUNK_SIZE, last_col / CHUNK_SIZE),
+        );
+
+        // the outer 2 pixels are never simulated so rules can look at their
+        // neighbours without bounds checks
+        let inner = DirtyRect {
+            top: 2,
+            bottom: size - 3,
+            left: 2,
+            right: size - 3,
         };
 
-        for point in pts {
-            self.update_px(point.0, point.1, player);
-        
+        // chunks are split into 4 phases. every chunk in a phase runs at the
+        // same time, then whatever they changed outside their own pixels is
+        // applied before the next phase starts
+        let mut phases: [Vec<(usize, DirtyRect)>; 4] = Default::default();
+        for chunk_row in (first_row / CHUNK_SIZE..=last_row / CHUNK_SIZE).rev() {
+            for chunk_col in first_col / CHUNK_SIZE..=last_col / CHUNK_SIZE {
+                if let Some(rect) = self.chunks.get(chunk_row, chunk_col).current.and_then(|r| r.clip(inner)) {
+                    phases[Chunks::phase(chunk_row, chunk_col)]
+                        .push((chunk_row * self.chunks.per_side + chunk_col, rect));
+                }
+            }
         }
-        if self.realistic_fluid {
-            self.move_fluids(player.x as i32, player.y as i32)
-        };
 
-        for (col, row) in self.sky_light.iter_mut().enumerate() {
-            if *row <= self.size as usize - 2
-                && (self.grid[(*row + 1 as usize, col)].is_airy()
-                    || self.grid[(*row, col)] == Pixel::Glass)
-            {
-                *row += 1;
+        // each chunk gets its own rng so the result doesn't depend on which
+        // thread gets there first
+        let tick_seed = self.rng.sim.u64(..);
+
+        for jobs in phases {
+            let mut cells = self.take_cells();
+            let shared = cells.share(size);
+
+            let changes: Vec<WorkerChanges> = jobs
+                .par_iter()
+                .map(|(index, rect)| {
+                    let rng = Rng::with_seed(tick_seed.wrapping_add(*index as u64));
+                    let mut worker = PixelWorker::new(&shared, &self.sun, &self.wind, self.tick, *focus, rng)
+                        .realistic_fluid(self.realistic_fluid)
+                        .daylight(self.clock.daylight())
+                        .growth(self.clock.season().growth());
+                    worker.sweep(*rect);
+                    worker.finish().0
+                })
+                .collect();
+
+            self.restore_cells(cells);
+            for change in changes {
+                self.apply_changes(change);
+            }
+        }
+
+        self.update_particles();
+        self.components.update(&self.grid);
+        self.spawn_fish(first_row..=last_row, first_col..=last_col);
+
+        // light is cheap compared to the pixel rules, so everything on screen
+        // gets it every tick
+        let (x, y, w, h) = focus.view;
+        let view_rows = (y.max(1.0) as usize)..=((y + h) as usize).min(size - 2);
+        let view_cols = (x.max(1.0) as usize)..=((x + w) as usize).min(size - 2);
+        for row in view_rows.clone() {
+            for col in view_cols.clone() {
+                let px = self.grid[(row, col)];
+                self.block_percent
+                    .insert(px, self.block_percent.get(&px).unwrap_or(&0) + 1);
             }
         }
+        self.with_worker(|worker| {
+            for row in view_rows.clone() {
+                for col in view_cols.clone() {
+                    worker.update_light(col, row);
+                }
+            }
+        });
+        self.update_glow(view_rows, view_cols);
 
         // self.detect_biome(player);
     }
 
+    /// first and last row (or col) inside the simulation distance of `centre`
+    fn sim_range(&self, centre: f32) -> (usize, usize) {
+        let size = self.size as i32;
+        if self.settings.sim_distance >= size {
+            return (2, size as usize - 3);
+        }
+        (
+            (centre as i32 - self.settings.sim_distance).clamp(2, size - 3) as usize,
+            (centre as i32 + self.settings.sim_distance).clamp(2, size - 3) as usize,
+        )
+    }
+
+    /// pixels that fell asleep never get blown, so each time the wind is
+    /// worked out again everything it could push gets woken up
+    fn wake_blown(&mut self, rows: RangeInclusive<usize>, cols: RangeInclusive<usize>) {
+        for row in rows {
+            for col in cols.clone() {
+                let (x, y) = self.wind.at(row, col);
+                if x.abs() + y.abs() < CALM_WIND || self.grid[(row, col)].material().windage <= 0.0 {
+                    continue;
+                }
+                if self.grid[(row - 1, col)].is_airy()
+                    || self.grid[(row, col - 1)].is_airy()
+                    || self.grid[(row, col + 1)].is_airy()
+                {
+                    self.chunks.wake(row, col);
+                }
+            }
+        }
+    }
+
+    /// fish show up in random bits of water near the player, as long as it is
+    /// big enough to swim in. water that is sitting still is asleep, so this
+    /// can't be done in `update_px`
+    fn spawn_fish(&mut self, rows: RangeInclusive<usize>, cols: RangeInclusive<usize>) {
+        let area = (rows.end() - rows.start() + 1) * (cols.end() - cols.start() + 1);
+        let max_fish = ((self.settings.sim_distance * 2).pow(2) / 6000) as usize;
+
+        for _ in 0..area / 2000 + 1 {
+            let row = self.rng.sim.usize(rows.clone());
+            let col = self.rng.sim.usize(cols.clone());
+
+            if self.grid[(row, col)] == Pixel::Water
+                && self.fluid_body(row, col).is_some_and(|body| self.components.size(body) >= FISH_WATER)
+                && self.rng.sim.f32() > 0.5
+                && self.entities.len() < max_fish
+            {
+                self.spawn_entity(EntityType::Fish { air: 20.0 }, col as f32, row as f32);
+            }
+        }
+    }
+
+    /// moves every entity one step, dropping the ones that died
+    pub fn update_entities(&mut self, boid_data: &Vec<BoidData>, delta: f32) {
+        let grid = &self.grid;
+        let wind = &self.wind;
+        let rng = &mut self.rng.sim;
+        self.entities
+            .retain_mut(|entity| entity.update(grid, wind, boid_data, delta, rng));
+    }
+
     // fn detect_biome(&mut self, player: &Player) {
 
     //     let height = player.y / self.size as f32;
@@ -106,346 +272,1001 @@ impl Map {
 
     // }
 
-    /// TODO: https://www.codeproject.com/Articles/16405/Queue-Linear-Flood-Fill-A-Fast-Flood-Fill-Algorith
-    pub fn move_fluids(&mut self, player_x: i32, player_y: i32) {
-        // self.detected_air =
-        //     Grid::from_vec(vec![0; self.size.pow(2) as usize], self.size as usize);
-        let mut check_water: Vec<(usize, usize)> = vec![];
+    /// swaps 2 pixels and also updates texture
+    pub fn swap_px(&mut self, a: (i32, i32), b: (i32, i32)) {
+        self.with_worker(|worker| worker.swap_px(a, b));
+    }
+
+    pub fn ignite_px(&mut self, col: i32, row: i32, force: bool) -> bool {
+        self.with_worker(|worker| worker.ignite_px(col, row, force))
+    }
 
-        let mut air_id: i16;
-        let mut check: Vec<(usize, usize)> = vec![];
+    /// runs pixel rules outside of `update_state`, e.g. for the player or
+    /// fluids. uses the map's own rng so it stays deterministic
+    pub fn with_worker<R>(&mut self, f: impl FnOnce(&mut PixelWorker) -> R) -> R {
+        let rng = std::mem::replace(&mut self.rng.sim, Rng::with_seed(0));
+        let mut cells = self.take_cells();
+        let shared = cells.share(self.size as usize);
+
+        let mut worker = PixelWorker::new(&shared, &self.sun, &self.wind, self.tick, SimFocus::default(), rng)
+            .realistic_fluid(self.realistic_fluid)
+            .daylight(self.clock.daylight())
+            .growth(self.clock.season().growth());
+        let result = f(&mut worker);
+        let (changes, rng) = worker.finish();
+
+        self.restore_cells(cells);
+        self.rng.sim = rng;
+        self.apply_changes(changes);
+        result
+    }
 
-        for a in &self.update_texture_px {
-            self.detected_air[*a] = 0
+    fn take_cells(&mut self) -> MapCells {
+        MapCells {
+            grid: std::mem::replace(&mut self.grid, Grid::from_vec(vec![], 0)).into_vec(),
+            light_mask: std::mem::replace(&mut self.light_mask, Grid::from_vec(vec![], 0)).into_vec(),
+            updated_on: std::mem::replace(&mut self.updated_on, Grid::from_vec(vec![], 0)).into_vec(),
+            temperature: std::mem::replace(&mut self.temperature, Grid::from_vec(vec![], 0)).into_vec(),
+            meta: std::mem::replace(&mut self.meta, Grid::from_vec(vec![], 0)).into_vec(),
+            fluid: std::mem::replace(&mut self.fluid, Grid::from_vec(vec![], 0)).into_vec(),
+            stress: std::mem::replace(&mut self.stress, Grid::from_vec(vec![], 0)).into_vec(),
         }
+    }
 
-        for (row, col) in &self.update_texture_px {
-            let row: usize = (*row).clamp(2, self.size as usize - 2);
-            let col: usize = (*col).clamp(2, self.size as usize - 2);
+    fn restore_cells(&mut self, cells: MapCells) {
+        let size = self.size as usize;
+        self.grid = Grid::from_vec(cells.grid, size);
+        self.light_mask = Grid::from_vec(cells.light_mask, size);
+        self.updated_on = Grid::from_vec(cells.updated_on, size);
+        self.temperature = Grid::from_vec(cells.temperature, size);
+        self.meta = Grid::from_vec(cells.meta, size);
+        self.fluid = Grid::from_vec(cells.fluid, size);
+        self.stress = Grid::from_vec(cells.stress, size);
+    }
 
-            if (row as i32) < player_y - self.settings.sim_distance / 2
-                || row as i32 > player_y + self.settings.sim_distance / 2
-                || col as i32 > player_x + self.settings.sim_distance / 2
-                || (col as i32) < player_x - self.settings.sim_distance / 2
-            {
-                continue;
+    fn apply_changes(&mut self, changes: WorkerChanges) {
+        for &(row, col) in &changes.dirty {
+            self.components.changed((row, col));
+            if !self.grid[(row, col)].lets_light_through() {
+                self.sun.block(row, col);
             }
+        }
+        self.update_texture_px.extend(changes.dirty);
+        self.update_light_px.extend(changes.lit);
+        for (row, col) in changes.woken {
+            self.chunks.wake(row, col);
+        }
+        self.add_particles(changes.thrown);
+        for (row, col) in changes.fuses {
+            self.light_fuse(row, col);
+        }
+        for blast in changes.blasts {
+            self.push_entities(blast);
+            self.blasts.push(blast);
+        }
+    }
+}
 
-            let current_row_col = self.grid[(row, col)];
-            air_id = 0;
-            if self.detected_air[(row, col)] == 0 && current_row_col == Pixel::Air {
-                let mut alone = true;
-                for f in [
-                    (row + 1, col),
-                    (row - 1, col),
-                    (row, col + 1),
-                    (row, col - 1),
-                ] {
-                    alone = alone && !(self.grid[f] == Pixel::Air);
-                }
-                if alone {
+/// the grids pixel rules write to, taken out of the map while workers run
+struct MapCells {
+    grid: Vec<Pixel>,
+    light_mask: Vec<f32>,
+    updated_on: Vec<u32>,
+    temperature: Vec<f32>,
+    meta: Vec<PixelMeta>,
+    fluid: Vec<f32>,
+    stress: Vec<u8>,
+}
+
+impl MapCells {
+    fn share(&mut self, cols: usize) -> SharedCells<'_> {
+        SharedCells {
+            grid: SharedGrid::new(&mut self.grid, cols),
+            light_mask: SharedGrid::new(&mut self.light_mask, cols),
+            updated_on: SharedGrid::new(&mut self.updated_on, cols),
+            temperature: SharedGrid::new(&mut self.temperature, cols),
+            meta: SharedGrid::new(&mut self.meta, cols),
+            fluid: SharedGrid::new(&mut self.fluid, cols),
+            stress: SharedGrid::new(&mut self.stress, cols),
+        }
+    }
+}
+
+pub struct SharedCells<'a> {
+    grid: SharedGrid<'a, Pixel>,
+    light_mask: SharedGrid<'a, f32>,
+    updated_on: SharedGrid<'a, u32>,
+    temperature: SharedGrid<'a, f32>,
+    meta: SharedGrid<'a, PixelMeta>,
+    fluid: SharedGrid<'a, f32>,
+    stress: SharedGrid<'a, u8>,
+}
+
+/// what a worker changed that the map has to know about
+struct WorkerChanges {
+    dirty: Vec<(usize, usize)>,
+    woken: Vec<(usize, usize)>,
+    lit: Vec<(usize, usize)>,
+    thrown: Vec<Particle>,
+    fuses: Vec<(usize, usize)>,
+    blasts: Vec<Blast>,
+}
+
+/// runs the pixel rules for one chunk. it can only see pixels within
+/// `MAX_REACH` of the chunk, everything else it changes is kept until
+/// `finish` so chunks can run side by side
+pub struct PixelWorker<'a> {
+    cells: &'a SharedCells<'a>,
+    sun: &'a Sun,
+    wind: &'a Wind,
+    size: usize,
+    tick: u64,
+    focus: SimFocus,
+    rng: Rng,
+    realistic_fluid: bool,
+    /// how much sun is getting to the open sky, see `Clock::daylight`
+    daylight: f32,
+    /// how fast plants grow, see `Season::growth`
+    growth: f32,
+    dirty: Vec<(usize, usize)>,
+    woken: Vec<(usize, usize)>,
+    lit: Vec<(usize, usize)>,
+    thrown: Vec<Particle>,
+    fuses: Vec<(usize, usize)>,
+    blasts: Vec<Blast>,
+}
+
+impl<'a> PixelWorker<'a> {
+    fn new(
+        cells: &'a SharedCells<'a>,
+        sun: &'a Sun,
+        wind: &'a Wind,
+        tick: u64,
+        focus: SimFocus,
+        rng: Rng,
+    ) -> PixelWorker<'a> {
+        PixelWorker {
+            cells,
+            sun,
+            wind,
+            size: sun.size(),
+            tick,
+            focus,
+            rng,
+            realistic_fluid: false,
+            daylight: 1.0,
+            growth: 1.0,
+            dirty: vec![],
+            woken: vec![],
+            lit: vec![],
+            thrown: vec![],
+            fuses: vec![],
+            blasts: vec![],
+        }
+    }
+
+    /// lets liquids flow by pressure, see `flow_liquid`
+    fn realistic_fluid(mut self, on: bool) -> PixelWorker<'a> {
+        self.realistic_fluid = on;
+        self
+    }
+
+    /// lights the open sky for the time of day
+    fn daylight(mut self, daylight: f32) -> PixelWorker<'a> {
+        self.daylight = daylight;
+        self
+    }
+
+    /// speeds up or slows down plants for the season
+    fn growth(mut self, growth: f32) -> PixelWorker<'a> {
+        self.growth = growth;
+        self
+    }
+
+    fn finish(self) -> (WorkerChanges, Rng) {
+        (
+            WorkerChanges {
+                dirty: self.dirty,
+                woken: self.woken,
+                lit: self.lit,
+                thrown: self.thrown,
+                fuses: self.fuses,
+                blasts: self.blasts,
+            },
+            self.rng,
+        )
+    }
+
+    /// updates every pixel in `rect` bottom to top so falling things only
+    /// move once, alternating left/right each tick so liquids don't drift to
+    /// one side
+    fn sweep(&mut self, rect: DirtyRect) {
+        let left_to_right = self.tick % 2 == 0;
+
+        for row in (rect.top..=rect.bottom).rev() {
+            for i in 0..=(rect.right - rect.left) {
+                let col = if left_to_right { rect.left + i } else { rect.right - i };
+
+                if self.updated_on((row, col)) == self.tick as u32 {
                     continue;
                 }
-                check = vec![(row, col)];
-                air_id = fastrand::i16(i16::MIN..i16::MAX);
+                self.update_px(col as i32, row as i32);
             }
-            while !check.is_empty() {
-                let a = check.pop().unwrap_or((0, 0));
+        }
+    }
 
-                if a.1 < 2
-                    || a.1 > self.size as usize - 2
-                    || a.0 > self.size as usize - 2
-                    || a.0 < 2
-                {
-                    continue;
-                }
+    pub fn get(&self, pos: (usize, usize)) -> Pixel {
+        // SAFETY: workers only run side by side when they are too far apart
+        // to reach each other's pixels, see `MAX_REACH`
+        unsafe { self.cells.grid.get(pos) }
+    }
 
-                self.detected_air[a] = air_id;
+    pub fn get_checked(&self, row: i32, col: i32) -> Option<Pixel> {
+        if row < 0 || col < 0 || row >= self.size as i32 || col >= self.size as i32 {
+            return None;
+        }
+        Some(self.get((row as usize, col as usize)))
+    }
 
-                let row = a.0;
-                let col = a.1;
+    fn updated_on(&self, pos: (usize, usize)) -> u32 {
+        // SAFETY: see `get`
+        unsafe { self.cells.updated_on.get(pos) }
+    }
 
-                if (row as i32) < player_y - self.settings.sim_distance / 2
-                    || row as i32 > player_y + self.settings.sim_distance / 2
-                    || col as i32 > player_x + self.settings.sim_distance / 2
-                    || (col as i32) < player_x - self.settings.sim_distance / 2
-                {
-                    continue;
-                }
+    pub fn temperature(&self, pos: (usize, usize)) -> f32 {
+        // SAFETY: see `get`
+        unsafe { self.cells.temperature.get(pos) }
+    }
 
-                if row < self.size as usize - 3 && self.grid[(row + 1, col)].fluid() {
-                    check_water.push((row + 1, col));
-                }
+    pub fn set_temperature(&mut self, pos: (usize, usize), temperature: f32) {
+        // SAFETY: see `get`
+        unsafe { self.cells.temperature.set(pos, temperature) };
+    }
 
-                for i in [
-                    (row + 1, col),
-                    (row - 1, col),
-                    (row, col + 1),
-                    (row, col - 1),
-                ] {
-                    if self.detected_air[i] == air_id {
-                        continue;
-                    }
-                    if self.grid[i] == current_row_col {
-                        check.push(i)
-                    }
-                }
+    pub fn meta(&self, pos: (usize, usize)) -> PixelMeta {
+        // SAFETY: see `get`
+        unsafe { self.cells.meta.get(pos) }
+    }
+
+    pub fn set_meta(&mut self, pos: (usize, usize), meta: PixelMeta) {
+        // SAFETY: see `get`
+        unsafe { self.cells.meta.set(pos, meta) };
+    }
+
+    pub fn stress(&self, pos: (usize, usize)) -> u8 {
+        // SAFETY: see `get`
+        unsafe { self.cells.stress.get(pos) }
+    }
+
+    pub fn set_stress(&mut self, pos: (usize, usize), stress: u8) {
+        // SAFETY: see `get`
+        unsafe { self.cells.stress.set(pos, stress) };
+    }
+
+    pub fn fluid(&self, pos: (usize, usize)) -> f32 {
+        // SAFETY: see `get`
+        unsafe { self.cells.fluid.get(pos) }
+    }
+
+    pub fn set_fluid(&mut self, pos: (usize, usize), fluid: f32) {
+        // SAFETY: see `get`
+        unsafe { self.cells.fluid.set(pos, fluid) };
+    }
+
+    fn light(&self, pos: (usize, usize)) -> f32 {
+        // SAFETY: see `get`
+        unsafe { self.cells.light_mask.get(pos) }
+    }
+
+    /// replaces a pixel, redrawing it and waking everything around it
+    pub fn set_px(&mut self, row: usize, col: usize, pixel: Pixel) {
+        // SAFETY: see `get`
+        unsafe { self.cells.grid.set((row, col), pixel) };
+        self.set_meta((row, col), self.meta((row, col)).became(pixel));
+        self.set_fluid((row, col), if pixel.flows() { 1.0 } else { 0.0 });
+        self.set_stress((row, col), u8::MAX);
+        if let Some((heat, _)) = pixel.material().heat {
+            self.set_temperature((row, col), self.temperature((row, col)).max(heat));
+        }
+        self.dirty.push((row, col));
+        self.woken.push((row, col));
+    }
+
+    /// swaps 2 pixels and also updates texture
+    pub fn swap_px(&mut self, a: (i32, i32), b: (i32, i32)) {
+        let a = (a.0 as usize, a.1 as usize);
+        let b = (b.0 as usize, b.1 as usize);
+        let temp1 = (self.get(a), self.temperature(a), self.meta(a), self.fluid(a));
+        let temp2 = (self.get(b), self.temperature(b), self.meta(b), self.fluid(b));
+        for ((row, col), (pixel, temperature, meta, fluid)) in [(a, temp2), (b, temp1)] {
+            self.set_px(row, col, pixel);
+            self.set_temperature((row, col), temperature);
+            self.set_meta((row, col), meta);
+            self.set_fluid((row, col), fluid);
+            // SAFETY: see `get`
+            unsafe { self.cells.updated_on.set((row, col), self.tick as u32) };
+        }
+    }
+
+    /// soil takes on moisture from anything wet next to it (or from wetter
+    /// soil) and dries out slowly otherwise
+    fn soak(&mut self, row: usize, col: usize) {
+        let mut meta = self.meta((row, col));
+
+        let mut moisture = meta.moisture.saturating_sub(1);
+        for pos in [(row - 1, col), (row + 1, col), (row, col - 1), (row, col + 1)] {
+            let neighbour = self.get(pos);
+            moisture = moisture.max(neighbour.material().wetness);
+            if neighbour == self.get((row, col)) {
+                moisture = moisture.max(self.meta(pos).moisture.saturating_sub(SOAK_LOSS));
             }
         }
 
-        let mut fluid_surfaces: Vec<Vec<(usize, usize)>> = vec![];
-        self.detected_fluids =
-            Grid::from_vec(vec![false; self.size.pow(2) as usize], self.size as usize);
-        for (row, col) in check_water {
-            if (row as i32) < player_y - self.settings.sim_distance
-                || row as i32 > player_y + self.settings.sim_distance
-                || col as i32 > player_x + self.settings.sim_distance
-                || (col as i32) < player_x - self.settings.sim_distance
+        if moisture != meta.moisture {
+            meta.moisture = moisture;
+            self.set_meta((row, col), meta);
+            self.dirty.push((row, col));
+            self.woken.push((row, col));
+        }
+    }
+
+    /// runs the first reaction of the pixel's material that comes up with
+    /// something it touches. a pixel that touches something it could react
+    /// with stays awake until it does
+    fn react(&mut self, row: usize, col: usize) {
+        let px = self.get((row, col));
+        let reactions = &px.material().reactions;
+        if reactions.is_empty() {
+            return;
+        }
+
+        let mut neighbours = [(row - 1, col), (row + 1, col), (row, col - 1), (row, col + 1)];
+        self.rng.shuffle(&mut neighbours);
+
+        let mut touching = false;
+        for reaction in reactions {
+            if reaction
+                .catalyst
+                .is_some_and(|catalyst| !neighbours.iter().any(|pos| self.get(*pos) == catalyst))
             {
                 continue;
             }
 
-            if self.grid[(row, col)].fluid() && !self.detected_fluids[(row, col)] {
-                let mut alone = true;
-                for f in [
-                    (row + 1, col),
-                    (row - 1, col),
-                    (row, col + 1),
-                    (row, col - 1),
-                ] {
-                    alone = alone && !self.grid[f].fluid()
-                }
-                if alone {
+            for pos in neighbours {
+                if self.get(pos) != reaction.with {
                     continue;
                 }
-                fluid_surfaces.insert(0, vec![]);
-                check = vec![(row, col)];
-            }
-            let current_row_col = self.grid[(row, col)];
-            while !check.is_empty() {
-                let a = check.pop().unwrap_or((0, 0));
-                if a.1 < 2
-                    || a.1 > self.size as usize - 2
-                    || a.0 > self.size as usize - 2
-                    || a.0 < 2
-                {
-                    continue;
+                touching = true;
+                if self.rng.f32() < reaction.chance {
+                    self.set_px(row, col, reaction.into);
+                    self.set_px(pos.0, pos.1, reaction.other_into);
+                    return;
                 }
+            }
+        }
 
-                self.detected_fluids[a] = true;
-                if self.grid[(a.0 - 1, a.1)] == Pixel::Air {
-                    fluid_surfaces[0].push(a)
-                }
-                let row = a.0;
-                let col = a.1;
+        if touching {
+            self.woken.push((row, col));
+        }
+    }
 
-                if (row as i32) < player_y - self.settings.sim_distance
-                    || row as i32 > player_y + self.settings.sim_distance
-                    || col as i32 > player_x + self.settings.sim_distance
-                    || (col as i32) < player_x - self.settings.sim_distance
-                {
-                    continue;
-                }
+    /// moves liquid between a cell and the same liquid around it: down until
+    /// the cell below is full (and squashed by what's above it), sideways to
+    /// even out, and up by whatever the pressure from below pushes out. that
+    /// pressure is what levels out liquid joined at the bottom
+    fn flow_liquid(&mut self, row: usize, col: usize) {
+        let px = self.get((row, col));
+        let before = self.fluid((row, col));
+        let mut fluid = before;
+
+        let below = (row + 1, col);
+        if self.get(below) == px {
+            let flow = (settled_below(fluid + self.fluid(below)) - self.fluid(below)) / 2.0;
+            fluid -= self.move_fluid(below, flow.min(fluid));
+        }
 
-                for i in [
-                    (row + 1, col),
-                    (row - 1, col),
-                    (row, col + 1),
-                    (row, col - 1),
-                ] {
-                    if !self.detected_fluids[i] && self.grid[i] == current_row_col {
-                        check.push(i)
-                    }
-                }
-                // }
+        for side in [(row, col - 1), (row, col + 1)] {
+            if self.get(side) == px {
+                let side_fluid = self.fluid(side);
+                // cells that aren't full pour into fuller ones next to them
+                // rather than evening out, so a puddle ends up with as many
+                // pixels as it holds liquid
+                let flow = if fluid < 1.0 && side_fluid >= fluid && side_fluid < 1.0 {
+                    fluid.min(1.0 - side_fluid)
+                } else {
+                    (fluid - side_fluid) / 3.0
+                };
+                fluid -= self.move_fluid(side, flow);
             }
         }
 
-        fluid_surfaces.retain(|f| f.len() > 3);
+        let above = (row - 1, col);
+        if self.get(above) == px {
+            let flow = (fluid - settled_below(fluid + self.fluid(above))) / 2.0;
+            fluid -= self.move_fluid(above, flow);
+        } else if self.get(above) == Pixel::Air {
+            let flow = fluid - settled_below(fluid);
+            if flow >= MIN_PUSH {
+                self.set_px(above.0, above.1, px);
+                self.set_fluid(above, flow);
+                fluid -= flow;
+            }
+        }
 
-        if fluid_surfaces.is_empty() {
-            return;
+        if fluid < MIN_FLUID {
+            if self.get(below) == px {
+                self.set_fluid(below, self.fluid(below) + fluid);
+            }
+            self.set_px(row, col, Pixel::Air);
+        } else if fluid != before {
+            self.set_fluid((row, col), fluid);
+            self.woken.push((row, col));
         }
+    }
 
-        for surface in fluid_surfaces {
-            let swap = fastrand::choose_multiple(surface.iter(), 2);
-            if self.detected_air[((swap[1].0 - 1), swap[1].1)]
-                != self.detected_air[((swap[0].0 - 1), swap[0].1)]
-                || self.detected_air[((swap[1].0 - 1), swap[1].1)] == 0
-                || self.detected_air[((swap[0].0 - 1), swap[1].1)] == 0
-            {
-                continue;
+    /// gives `flow` to the same liquid at `to`, returning how much actually
+    /// moved
+    fn move_fluid(&mut self, to: (usize, usize), flow: f32) -> f32 {
+        if flow < MIN_FLOW {
+            return 0.0;
+        }
+        self.set_fluid(to, self.fluid(to) + flow);
+        self.woken.push(to);
+        flow
+    }
+
+    /// the rule every gas moves by. a cell evens out with thinner cells of the
+    /// same gas and bleeds into open air beside and above it, but never below
+    /// it, so gas gathers under ceilings and fills a cave from the top down.
+    /// it rises as much as it is lighter than air and runs along whatever it
+    /// is stuck under
+    fn flow_gas(&mut self, col: i32, row: i32, px: Pixel, fade: f32) {
+        let (u_row, u_col) = (row as usize, col as usize);
+        let before = self.fluid((u_row, u_col));
+        let mut amount = before * (1.0 - fade);
+
+        let above = (u_row - 1, u_col);
+        for pos in [above, (u_row, u_col - 1), (u_row, u_col + 1), (u_row + 1, u_col)] {
+            let other = self.get(pos);
+            if other == px {
+                let flow = (amount - self.fluid(pos)) / 4.0;
+                amount -= self.move_fluid(pos, flow);
+            } else if other == Pixel::Air && pos.0 <= u_row && amount / 4.0 >= MIN_GAS {
+                let flow = amount / 4.0;
+                self.set_px(pos.0, pos.1, px);
+                self.set_fluid(pos, flow);
+                self.set_temperature(pos, self.temperature((u_row, u_col)));
+                amount -= flow;
             }
-            if swap[0].0 > swap[1].0 {
-                self.swap_px(
-                    (swap[1].0 as i32, swap[1].1 as i32),
-                    ((swap[0].0 - 1) as i32, swap[0].1 as i32),
-                )
+        }
+
+        // thin cells hand what's left to the rest of the cloud, so it shrinks
+        // from the edges instead of vanishing all at once
+        if amount < MIN_GAS {
+            let near = [above, (u_row, u_col - 1), (u_row, u_col + 1), (u_row + 1, u_col)];
+            if let Some(pos) = near.into_iter().find(|pos| self.get(*pos) == px) {
+                self.move_fluid(pos, amount);
             }
-            if swap[0].0 < swap[1].0 {
-                self.swap_px(
-                    (swap[0].0 as i32, swap[0].1 as i32),
-                    ((swap[1].0 - 1) as i32, swap[1].1 as i32),
-                )
+            self.set_px(u_row, u_col, Pixel::Air);
+            return;
+        }
+        if amount != before {
+            self.set_fluid((u_row, u_col), amount);
+        }
+
+        if self.get(above) == Pixel::Air {
+            let air = Pixel::Air.fluid_density().unwrap_or(1) as f32;
+            let buoyancy = 1.0 - px.fluid_density().unwrap_or(0) as f32 / air;
+            if self.rng.f32() < buoyancy {
+                self.swap_px((row, col), (row - 1, col));
             }
+        } else {
+            self.spread(col, row, |other| other == Pixel::Air);
         }
     }
 
-    /// swaps 2 pixels and also updates texture
-    pub fn swap_px(&mut self, a: (i32, i32), b: (i32, i32)) {
-        let temp1 = self.grid[(a.0 as usize, a.1 as usize)];
-        self.grid[(a.0 as usize, a.1 as usize)] = self.grid[(b.0 as usize, b.1 as usize)];
-        self.grid[(b.0 as usize, b.1 as usize)] = temp1;
-        self.update_texture_px.insert((b.0 as usize, b.1 as usize));
-        self.update_texture_px.insert((a.0 as usize, a.1 as usize));
+    /// evens out the temperature between a pixel and its neighbours, then
+    /// lets it melt, boil or freeze if it went past one of its limits
+    fn conduct_heat(&mut self, row: usize, col: usize) {
+        let material = self.get((row, col)).material();
+        let before = self.temperature((row, col));
+
+        let mut temperature = before;
+        for pos in [(row - 1, col), (row + 1, col), (row, col - 1), (row, col + 1)] {
+            let conductivity = material.conductivity.min(self.get(pos).material().conductivity);
+            temperature += (self.temperature(pos) - before) * conductivity / 4.0;
+        }
+        temperature += (AMBIENT_TEMPERATURE - temperature) * HEAT_LOSS;
+        if let Some((heat, rate)) = material.heat {
+            temperature += (heat - temperature) * rate;
+        }
+
+        self.set_temperature((row, col), temperature);
+        if (temperature - before).abs() > SETTLED_TEMPERATURE {
+            self.woken.push((row, col));
+        }
+
+        let into = match (material.when_hot, material.when_cold) {
+            (Some((limit, into)), _) if temperature >= limit => into,
+            (_, Some((limit, into))) if temperature <= limit => into,
+            _ => return,
+        };
+        // boiling and condensing keep how much there was, so a thin wisp of
+        // steam only turns back into a drop of water
+        let amount = self.fluid((row, col));
+        self.set_px(row, col, into);
+        if amount > 0.0 && into.flows() {
+            self.set_fluid((row, col), amount);
+        }
     }
 
+    /// tries to set a pixel alight, returning whether it caught, melted or
+    /// went off. `force` skips the chance of it not catching
     pub fn ignite_px(&mut self, col: i32, row: i32, force: bool) -> bool {
         if col < 0 || row < 0 || col >= self.size as i32 || row >= self.size as i32 {
             return false;
         }
 
-        if force
-            || fastrand::f32() * 100.0
-                >= self.grid[(row as usize, col as usize)].ignition_probability()
+        // fire melts ice and snow (and dries out mud) straight away instead
+        // of waiting for the heat to soak in, and nothing frozen catches
+        let pos = (row as usize, col as usize);
+        let material = self.get(pos).material();
+        if let Some((limit, into)) = material.when_hot.filter(|(limit, _)| material.solid && *limit < FLASH_TEMPERATURE) {
+            self.set_px(pos.0, pos.1, into);
+            self.set_temperature(pos, self.temperature(pos).max(limit));
+            return true;
+        }
+        if self.temperature(pos) <= FROZEN_TEMPERATURE {
+            return false;
+        }
+
+        if !force
+            && self.rng.f32() * 100.0
+                >= self.get((row as usize, col as usize)).ignition_probability()
         {
             return false;
         }
 
-        match self.grid[(row as usize, col as usize)] {
-            px if px.heat_product().is_some() => {
-                let extinguish = px.extinguish_fire();
-                self.grid[(row as usize, col as usize)] = px.heat_product().unwrap();
-                self.update_texture_px.insert((row as usize, col as usize));
+        let px = self.get((row as usize, col as usize));
+
+        if let Some(product) = px.heat_product(&mut self.rng) {
+            let pos = (row as usize, col as usize);
+            let fuel = self.meta(pos).fuel;
+            self.set_px(pos.0, pos.1, product);
+            // whatever caught fire is what the fire burns through
+            self.set_meta(pos, PixelMeta { fuel, ..self.meta(pos) });
+            self.set_temperature(pos, self.temperature(pos).max(FLASH_TEMPERATURE));
+
+            return true;
+        }
+
+        if let Some(strength) = px.material().explosion_strength {
+            self.explode(row as usize, col as usize, strength);
+            return true;
+        }
+
+        return false;
+    }
+
+    pub fn ignite_neighbors(&mut self, col: i32, row: i32, count: usize) -> i32 {
+        let mut neighbors = vec![(-1, 0), (0, -1), (1, 0), (0, 1)];
 
-                return extinguish;
+        self.rng.shuffle(&mut neighbors);
+
+        let mut ignited = 0;
+
+        for i in 0..count {
+            if self.ignite_px(col + neighbors[i].0, row + neighbors[i].1, false) {
+                ignited += 1;
             }
+        }
 
-            Pixel::Explosive => {
-                const RADIUS: i32 = 7;
+        ignited
+    }
 
-                self.grid[(row as usize, col as usize)] = Pixel::Air;
+    /// moves a pixel diagonally down to a random side, if there is room. when
+    /// that side is blocked it waits for another go rather than trying the
+    /// other side, but it mustn't fall asleep while that side is still open
+    fn slide_down(&mut self, col: i32, row: i32, px: Pixel) {
+        let (u_row, u_col) = (row as usize, col as usize);
+        let side = self.rng.choice([0, 2]).unwrap_or(1);
+        if self.get((u_row + 1, u_col - 1 + side)).less_dense(px) {
+            self.swap_px((row, col), (row + 1, col + side as i32 - 1));
+        } else if self.get((u_row + 1, u_col + 1 - side)).less_dense(px) {
+            self.woken.push((u_row, u_col));
+        }
+    }
 
-                for dr in -RADIUS..=RADIUS {
-                    let target_row = row + dr;
+    /// moves a pixel one step to a random side it is allowed to go
+    fn spread(&mut self, col: i32, row: i32, can_enter: impl Fn(Pixel) -> bool) {
+        let (u_row, u_col) = (row as usize, col as usize);
+        let side = self.rng.choice([0, 2]).unwrap_or(1);
+        if can_enter(self.get((u_row, u_col - 1 + side))) {
+            self.swap_px((row, col), (row, col + side as i32 - 1));
+        } else if can_enter(self.get((u_row, u_col - 1 + 2 - side))) {
+            self.swap_px((row, col), (row, col + 1 - side as i32));
+        }
+    }
 
-                    if target_row < 0 || target_row >= self.size as i32 {
-                        continue;
-                    }
+    /// the wind pushes things that catch it one cell along, as often as it is
+    /// strong and they have `windage`. gases and fire go wherever it takes
+    /// them, things that fall only get lifted off the top of a pile (hopping
+    /// up over whatever is in the way, which is how dunes creep) and anything
+    /// else only once nothing is holding it. returns whether the pixel moved
+    fn blow(&mut self, row: usize, col: usize) -> bool {
+        let px = self.get((row, col));
+        let material = px.material();
+        if material.windage <= 0.0 {
+            return false;
+        }
 
-                    let height = ((RADIUS * RADIUS - dr * dr) as f64).sqrt().round() as i32;
+        let (x, y) = self.wind.at(row, col);
+        let strength = x.abs() + y.abs();
+        if self.rng.f32() >= strength * material.windage {
+            return false;
+        }
 
-                    for dc in -height..=height {
-                        let target_col = col + dc;
+        let neighbours = [(row - 1, col), (row + 1, col), (row, col - 1), (row, col + 1)];
+        let free = if material.airy {
+            true
+        } else if material.density.is_some() {
+            self.get((row - 1, col)).is_airy()
+        } else {
+            neighbours.iter().all(|pos| self.get(*pos).is_airy())
+        };
+        if !free {
+            return false;
+        }
 
-                        if target_col < 0 || target_col >= self.size as i32 {
-                            continue;
-                        }
+        let mut to = if self.rng.f32() * strength < x.abs() {
+            (row, if x > 0.0 { col + 1 } else { col - 1 })
+        } else {
+            (if y < 0.0 { row - 1 } else { row + 1 }, col)
+        };
+        if to.0 == row && material.density.is_some() && !self.get(to).is_airy() {
+            to.0 -= 1;
+        }
+        let other = self.get(to);
+        if other == px || !other.is_airy() {
+            return false;
+        }
+        self.swap_px((row as i32, col as i32), (to.0 as i32, to.1 as i32));
+        true
+    }
 
-                        let target_px = &mut self.grid[(target_row as usize, target_col as usize)];
-                        self.update_texture_px
-                            .insert((target_row as usize, target_col as usize));
+    /// how far a pixel is from being held up, as far as the pixels touching
+    /// it are concerned
+    fn held_up(&self, pos: (usize, usize)) -> u8 {
+        let px = self.get(pos);
+        if self.meta(pos).flags & PixelMeta::LOOSE != 0 {
+            u8::MAX
+        } else if px.is_ground() {
+            0
+        } else if px.material().span.is_some() {
+            self.stress(pos)
+        } else {
+            u8::MAX
+        }
+    }
 
-                        if fastrand::f32() < 0.8 {
-                            if target_px.ignition_probability() > 0.0 {
-                                self.ignite_px(target_col, target_row, true);
-                            } else if *target_px != Pixel::Bedrock {
-                                *target_px = Pixel::Fire;
-                            }
-                        }
-                    }
+    /// built up pixels work out how far they are from the ground. resting on
+    /// something that is held up is free, reaching out sideways or hanging
+    /// down costs 1 a pixel, and past its `span` a pixel breaks loose. a
+    /// change wakes the pixels around it, so taking out a support spreads
+    /// through everything that leant on it
+    fn hold_up(&mut self, row: usize, col: usize, span: u8) {
+        let stress = if row >= self.size - 3 {
+            0
+        } else {
+            [((row + 1, col), 0), ((row - 1, col), 1), ((row, col - 1), 1), ((row, col + 1), 1)]
+                .into_iter()
+                .map(|(pos, cost)| self.held_up(pos).saturating_add(cost))
+                .min()
+                .unwrap_or(u8::MAX)
+        };
+
+        if stress > span {
+            let meta = self.meta((row, col));
+            self.set_meta((row, col), PixelMeta { flags: meta.flags | PixelMeta::LOOSE, ..meta });
+            self.set_stress((row, col), u8::MAX);
+            self.woken.push((row, col));
+        } else if stress != self.stress((row, col)) {
+            self.set_stress((row, col), stress);
+            self.woken.push((row, col));
+        }
+    }
+
+    /// a pixel that broke loose falls through air and liquid like sand, then
+    /// has to be held up again once it lands
+    fn fall_loose(&mut self, col: i32, row: i32) {
+        let (u_row, u_col) = (row as usize, col as usize);
+        let falls_into = |px: Pixel| px.is_airy() || px.fluid();
+
+        if falls_into(self.get((u_row + 1, u_col))) {
+            self.swap_px((row, col), (row + 1, col));
+            return;
+        }
+        let side = if self.rng.bool() { 1 } else { -1 };
+        for side in [side, -side] {
+            let next = (col + side) as usize;
+            if falls_into(self.get((u_row, next))) && falls_into(self.get((u_row + 1, next))) {
+                self.swap_px((row, col), (row + 1, col + side));
+                return;
+            }
+        }
+
+        let meta = self.meta((u_row, u_col));
+        self.set_meta((u_row, u_col), PixelMeta { flags: meta.flags & !PixelMeta::LOOSE, ..meta });
+        self.woken.push((u_row, u_col));
+    }
+
+    /// blows up whatever is at `row`, `col`. everything the blast has more
+    /// power left for than its blast resistance is destroyed: most of it
+    /// burns and some is thrown. explosives it reaches are lit rather than set
+    /// off here, see `Map::light_fuse`
+    pub fn explode(&mut self, row: usize, col: usize, strength: f32) {
+        let blast = Blast { row, col, strength };
+        self.set_px(row, col, Pixel::Air);
+
+        // worked out before anything is destroyed, so it doesn't matter which
+        // order the cells are gone through in
+        let radius = strength.ceil() as i32;
+        let mut destroyed = vec![];
+        for dr in -radius..=radius {
+            for dc in -radius..=radius {
+                let (target_row, target_col) = (row as i32 + dr, col as i32 + dc);
+                if target_row < 0 || target_col < 0 || target_row >= self.size as i32 || target_col >= self.size as i32 {
+                    continue;
+                }
+
+                let pos = (target_row as usize, target_col as usize);
+                let target_px = self.get(pos);
+                if target_px.is_airy() {
+                    continue;
+                }
+                let power = blast.power_at(pos.0 as f32 + 0.5, pos.1 as f32 + 0.5, |pos| self.get(pos));
+                if power > target_px.material().blast_resistance {
+                    destroyed.push((pos, power));
                 }
             }
+        }
 
-            _ => {}
+        for ((target_row, target_col), power) in destroyed {
+            let target_px = self.get((target_row, target_col));
+            if target_px.material().explosion_strength.is_some() {
+                self.fuses.push((target_row, target_col));
+            } else if self.rng.f32() < 0.8 {
+                if !self.ignite_px(target_col as i32, target_row as i32, true) {
+                    self.set_px(target_row, target_col, Pixel::Fire);
+                }
+            } else if self.rng.f32() < DEBRIS_CHANCE {
+                // thrown away from the middle, faster the more power reached it
+                let (x, y) = blast.direction(target_row as f32 + 0.5, target_col as f32 + 0.5);
+                let speed = (1.0 + self.rng.f32()) * power / strength;
+                self.throw_px(target_row, target_col, ParticleKind::Debris, x * speed, y * speed - 0.5);
+            } else {
+                self.set_px(target_row, target_col, Pixel::Air);
+            }
         }
 
-        return false;
+        for _ in 0..SPARKS {
+            let angle = self.rng.f32() * std::f32::consts::TAU;
+            let speed = 0.5 + self.rng.f32() * 1.5;
+            self.thrown.push(Particle::new(Pixel::Fire, ParticleKind::Spark, row, col, angle.cos() * speed, angle.sin() * speed));
+        }
+        self.blasts.push(blast);
     }
 
-    pub fn ignite_neighbors(&mut self, col: i32, row: i32, count: usize) -> i32 {
-        let mut neighbors = vec![(-1, 0), (0, -1), (1, 0), (0, 1)];
+    /// takes a pixel out of the grid and throws it as a particle, keeping
+    /// everything about it so it lands back the same
+    fn throw_px(&mut self, row: usize, col: usize, kind: ParticleKind, vx: f32, vy: f32) {
+        let pos = (row, col);
+        self.thrown.push(Particle {
+            meta: self.meta(pos),
+            fluid: self.fluid(pos),
+            temperature: self.temperature(pos),
+            ..Particle::new(self.get(pos), kind, row, col, vx, vy)
+        });
+        self.set_px(row, col, Pixel::Air);
+    }
 
-        fastrand::shuffle(&mut neighbors);
+    /// liquid that just fell onto the same liquid sometimes splashes a drop
+    /// back up. liquid that is itself still falling doesn't count, so a
+    /// stream only splashes where it hits the pool
+    fn splash(&mut self, row: usize, col: usize) {
+        let px = self.get((row, col));
+        let below = (row + 1, col);
+        if !px.fluid()
+            || self.get(below) != px
+            || self.updated_on(below) == self.tick as u32
+            || self.rng.f32() >= SPLASH_CHANCE
+        {
+            return;
+        }
+        let side = if self.rng.bool() { 1.0 } else { -1.0 };
+        let vx = side * (0.2 + self.rng.f32() * 0.4);
+        let vy = -(0.3 + self.rng.f32() * 0.5);
+        self.throw_px(row, col, ParticleKind::Debris, vx, vy);
+    }
 
-        let mut ignited = 0;
+    /// the rule every liquid spreads by. it sits still `viscosity` of the
+    /// time, otherwise it runs up to `dispersion` cells to one side through
+    /// anything lighter, stopping early to drip off ledges
+    fn flow_sideways(&mut self, col: i32, row: i32, px: Pixel) {
+        let material = px.material();
+        if self.rng.f32() < material.viscosity {
+            return;
+        }
 
-        for i in 0..count {
-            if self.ignite_px(col + neighbors[i].0, row + neighbors[i].1, false) {
-                ignited += 1;
+        let sides = if self.rng.bool() { [-1, 1] } else { [1, -1] };
+        for side in sides {
+            let mut reach = 0;
+            while reach < material.dispersion as i32 {
+                let next = col + side * (reach + 1);
+                if !self.get_checked(row, next).is_some_and(|other| other.less_dense(px)) {
+                    break;
+                }
+                reach += 1;
+                if self.get_checked(row + 1, next).is_some_and(|other| other.less_dense(px)) {
+                    break;
+                }
+            }
+            if reach > 0 {
+                self.swap_px((row, col), (row, col + side * reach));
+                return;
             }
         }
-
-        ignited
     }
 
-    pub fn update_px(&mut self, col: i32, row: i32, player: &Player) {
-        let num = fastrand::f32() * 100.0;
+    pub fn update_px(&mut self, col: i32, row: i32) {
+        let num = self.rng.f32() * 100.0;
         let u_row = row as usize;
         let u_col = col as usize;
 
-        let is_less_dense = self.grid[(u_row + 1, u_col)].less_dense(self.grid[(u_row, u_col)]);
+        let is_less_dense = self.get((u_row + 1, u_col)).less_dense(self.get((u_row, u_col)));
 
-        if is_less_dense && self.grid[(u_row, u_col)].fluid_density().is_some() {
-            if num > 85.0 || (!self.grid[(u_row, u_col)].is_airy()) {
+        if is_less_dense && self.get((u_row, u_col)).fluid_density().is_some() {
+            if num > 85.0 || (!self.get((u_row, u_col)).is_airy()) {
                 self.swap_px((row, col), (row + 1, col));
+                self.splash(u_row + 1, u_col);
             }
         }
 
-        let this_px = self.grid[(u_row, u_col)];
+        self.conduct_heat(u_row, u_col);
+
+        if self.realistic_fluid && self.get((u_row, u_col)).fluid() {
+            self.flow_liquid(u_row, u_col);
+        }
 
-        // updates based on what pixel is being updated
-        match this_px {
-            Pixel::Sand => {
-                if self.grid[(u_row + 1, u_col)] == Pixel::Sand {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row + 1, u_col - 1 + side)].less_dense(Pixel::Sand) {
-                        self.swap_px((row, col), (row + 1, col + side as i32 - 1));
+        self.react(u_row, u_col);
+
+        if self.blow(u_row, u_col) {
+            return;
+        }
+
+        let this_px = self.get((u_row, u_col));
+        let mut meta = self.meta((u_row, u_col));
+        meta.lifetime = meta.lifetime.saturating_add(1);
+        self.set_meta((u_row, u_col), meta);
+
+        if meta.flags & PixelMeta::LOOSE != 0 {
+            self.fall_loose(col, row);
+            return;
+        }
+        if let Some(span) = this_px.material().span {
+            self.hold_up(u_row, u_col, span);
+        }
+
+        // updates based on what the pixel's material does
+        match this_px.material().behaviour {
+            Behaviour::Static => {}
+
+            Behaviour::Powder => {
+                if self.get((u_row + 1, u_col)) == this_px {
+                    let viscosity = this_px.material().viscosity;
+                    if viscosity > 0.0 && self.rng.f32() < viscosity {
+                        // held back this time, so it mustn't fall asleep while
+                        // it still has somewhere to slide
+                        let open = [u_col - 1, u_col + 1]
+                            .into_iter()
+                            .any(|side| self.get((u_row + 1, side)).less_dense(this_px));
+                        if open {
+                            self.woken.push((u_row, u_col));
+                        }
+                    } else {
+                        self.slide_down(col, row, this_px);
                     }
                 }
             }
 
-            Pixel::Lamp => {
-                //     let radius = 6;
-                //     for row2  in-radius..radius {
-                //     for col2 in -radius..radius {
-                //     if row2*row2+col2*col2 <= radius*radius {
-                //         self.light_mask.set_pixel((col2 + col as i32) as u32, (row2 + row as i32) as u32, Color { r: 1.0, g: 1.0, b: 1.0, a: 0.0 });
-                //     }
-                //     }
-                // }
+            Behaviour::Drift { packs_into } => {
+                if self.get((u_row + 1, u_col)) == this_px {
+                    self.slide_down(col, row, this_px);
+                }
+                if (1..=PACK_DEPTH).all(|up| self.get_checked(row - up, col) == Some(this_px))
+                    && self.rng.f32() < PACK_CHANCE
+                {
+                    self.set_px(u_row, u_col, packs_into);
+                }
+            }
+
+            Behaviour::Soil { grows_into } => {
+                if self.get((u_row + 1, u_col)) == this_px && self.get((u_row - 1, u_col)) == this_px {
+                    self.slide_down(col, row, this_px);
+                }
+                if self.get((u_row - 1, u_col)) == Pixel::Air {
+                    self.set_px(u_row, u_col, grows_into);
+                }
+                self.soak(u_row, u_col);
+            }
+
+            Behaviour::Cover { reverts_to } => {
+                if !self.get((u_row - 1, u_col)).is_airy() || self.get((u_row + 1, u_col)).is_airy() {
+                    self.set_px(u_row, u_col, reverts_to);
+                }
+            }
+
+            Behaviour::Liquid => {
+                if !is_less_dense {
+                    self.flow_sideways(col, row, this_px);
+                }
             }
 
-            Pixel::LiveWood => {
-                if num > 97.5 {
-                    match fastrand::i32(0..100) {
-                        0..=20 if self.grid[(u_row - 1, u_col)].is_airy() => {
+            Behaviour::Gas { fade } => self.flow_gas(col, row, this_px, fade),
+
+            Behaviour::Fire { burns_out_to } => {
+                self.ignite_neighbors(col, row, 4);
+                // the wind leans flames over, so fire spreads faster downwind
+                let (wind, _) = self.wind.at(u_row, u_col);
+                if self.rng.f32() < wind.abs() {
+                    let side = wind.signum() as i32;
+                    for step in 1..=IGNITE_REACH as i32 {
+                        self.ignite_px(col + side * step, row, false);
+                    }
+                }
+                if meta.fuel > 0 {
+                    meta.fuel -= 1;
+                    self.set_meta((u_row, u_col), meta);
+                } else if num < 1.0 {
+                    self.set_px(u_row, u_col, burns_out_to);
+                }
+            }
+
+            Behaviour::Molten => {
+                if !is_less_dense {
+                    self.flow_sideways(col, row, this_px);
+                }
+
+                self.ignite_neighbors(col, row, 4);
+            }
+
+            Behaviour::Plant { leaf } => {
+                if num > 100.0 - 2.5 * self.growth {
+                    match self.rng.i32(0..100) {
+                        0..=20 if self.get((u_row - 1, u_col)).is_airy() => {
                             let px = (
                                 u_row - 1,
-                                (u_col as i32 + fastrand::choice([-1, 1]).unwrap_or(0)) as usize,
+                                (u_col as i32 + self.rng.choice([-1, 1]).unwrap_or(0)) as usize,
                             );
-                            if self.grid[px].is_airy() {
-                                self.grid[px] = Pixel::LiveWood;
-                                self.update_texture_px.insert(px);
+                            if self.get(px).is_airy() {
+                                self.set_px(px.0, px.1, this_px);
                             }
                         }
                         21..=93 => {
-                            if self.grid[(u_row - 1, u_col)].is_airy() {
-                                self.grid[(u_row - 1, u_col)] = Pixel::LiveWood;
-                                self.update_texture_px.insert((u_row - 1, u_col));
+                            if self.get((u_row - 1, u_col)).is_airy() {
+                                self.set_px(u_row - 1, u_col, this_px);
                             }
                         }
-                        _ if self.grid[(u_row - 1, u_col)].is_airy() => {
-                            let leaf_size: i32 = fastrand::i32(2..=4);
+                        _ if self.get((u_row - 1, u_col)).is_airy() => {
+                            let leaf_size: i32 = self.rng.i32(2..=4);
 
                             for x in -leaf_size..leaf_size {
                                 for y in -leaf_size..leaf_size {
                                     if x.pow(2) + y.pow(2) <= leaf_size.pow(2)
-                                        && self.grid.get(row + y, col + x) == Some(&Pixel::Air)
+                                        && self.get_checked(row + y, col + x) == Some(Pixel::Air)
                                     {
                                         let px = ((row + y) as usize, (col + x) as usize);
-                                        self.grid[px] = Pixel::Leaf;
-                                        self.update_texture_px.insert(px);
+                                        self.set_px(px.0, px.1, leaf);
                                     }
                                 }
                             }
@@ -454,194 +1275,122 @@ impl Map {
                     }
                 }
             }
-            Pixel::Seed => {
-                if num < 0.5 && self.grid[(u_row + 1, u_col)] == Pixel::Dirt {
-                    self.grid[(u_row, u_col)] = Pixel::LiveWood;
-                    self.update_texture_px.insert((u_row, u_col));
-                }
-            }
-            Pixel::Leaf => {}
 
-            Pixel::Candle => {
-                if self.grid[(u_row - 1, u_col)] == Pixel::Air && num > 80.0 {
-                    self.grid[(u_row - 1, u_col)] = Pixel::Fire;
-                    self.update_texture_px
-                        .insert((row as usize - 1, col as usize));
+            Behaviour::Seed { sprouts_into, soil } => {
+                if num < 0.5 * self.growth && self.get((u_row + 1, u_col)) == soil {
+                    self.set_px(u_row, u_col, sprouts_into);
                 }
-                self.ignite_px(u_col as i32, u_row as i32 - 1, false);
             }
 
-            Pixel::Explosive => {
-                if self.grid[(u_row + 1, u_col)] == Pixel::Explosive {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row + 1, u_col - 1 + side)].less_dense(Pixel::Explosive) {
-                        self.swap_px((row, col), (row + 1, col + side as i32 - 1));
-                    }
+            Behaviour::Candle { flame } => {
+                if self.get((u_row - 1, u_col)) == Pixel::Air && num > 80.0 {
+                    self.set_px(u_row - 1, u_col, flame);
                 }
+                self.ignite_px(u_col as i32, u_row as i32 - 1, false);
             }
+        }
 
-            Pixel::Dirt => {
-                if self.grid[(u_row + 1, u_col)] == Pixel::Dirt
-                    && self.grid[(u_row - 1, u_col)] == Pixel::Dirt
-                {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row + 1, u_col - 1 + side)].less_dense(Pixel::Dirt) {
-                        self.swap_px((row, col), (row + 1, col + side as i32 - 1));
-                    }
-                }
-                if self.grid[(u_row - 1, u_col)] == Pixel::Air {
-                    self.grid[(u_row, u_col)] = Pixel::Grass;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-            }
+        if this_px.is_restless() {
+            self.woken.push((u_row, u_col));
+        }
 
-            Pixel::Water => {
-                if !(is_less_dense) {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row, u_col - 1 + side)].is_airy() {
-                        self.swap_px((row, col), (row, col + side as i32 - 1));
-                    } else if self.grid[(u_row, u_col - 1 + 2 - side)].is_airy() {
-                        self.swap_px((row, col), (row, col + 1 - side as i32));
-                    }
-                }
+        // the view is lit every tick by `update_state`, this lets light
+        // creep around off screen too
+        if num > 90.0 && !self.focus.in_view(col as f32, row as f32) {
+            self.update_light(u_col, u_row);
+        }
+    }
 
-                if fastrand::f32() > 0.999
-                    && self.entities.len() < ((self.settings.sim_distance*2).pow(2) / 6000) as usize
-                {
-                    self.spawn_entity(EntityType::Fish { air: 20.0 }, col as f32, row as f32);
-                }
+    /// recomputes the darkness of one cell from its neighbours
+    pub fn update_light(&mut self, u_col: usize, u_row: usize) {
+        let light_mask_surroundings = [
+            self.light((u_row - 1, u_col - 1)),
+            self.light((u_row, u_col - 1)),
+            self.light((u_row + 1, u_col - 1)),
+            self.light((u_row - 1, u_col)),
+            self.light((u_row + 1, u_col)),
+            self.light((u_row - 1, u_col + 1)),
+            self.light((u_row, u_col + 1)),
+            self.light((u_row + 1, u_col + 1)),
+            if self.sun.lights(u_row, u_col) { SKY_DARKNESS + (1.0 - self.daylight) * NIGHT_DARKNESS } else { 1.0 },
+        ];
+
+        let mut light = 1.0;
+
+        for c in light_mask_surroundings {
+            if c <= light {
+                light = c;
             }
-            Pixel::Lava => {
-                if !is_less_dense && num < 10.0 {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row, u_col - 1 + side)].less_dense(Pixel::Lava) {
-                        self.swap_px((row, col), (row, col + side as i32 - 1));
-                    } else if self.grid[(u_row, u_col - 1 + 2 - side)].less_dense(Pixel::Lava) {
-                        self.swap_px((row, col), (row, col + 1 - side as i32));
-                    }
-                }
+        }
 
-                let num_extiniguish = self.ignite_neighbors(col, row, 4);
+        let light = (light + 0.15 * self.get((u_row, u_col)).light_emission()).clamp(0.0, 1.0);
+        if self.light((u_row, u_col)) != light {
+            // SAFETY: see `get`
+            unsafe { self.cells.light_mask.set((u_row, u_col), light) };
+            self.lit.push((u_row, u_col));
+        }
+    }
+}
 
-                if num < 1.5 * num_extiniguish as f32 {
-                    self.grid[(u_row, u_col)] = Pixel::Stone;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-            }
+/// how much of `total` liquid the lower of two stacked cells of it holds once
+/// settled. a full cell holds 1.0 plus `FLUID_COMPRESSION` more than the one
+/// above it
+fn settled_below(total: f32) -> f32 {
+    if total <= 1.0 {
+        total
+    } else if total < 2.0 + FLUID_COMPRESSION {
+        (1.0 + total * FLUID_COMPRESSION) / (1.0 + FLUID_COMPRESSION)
+    } else {
+        (total + FLUID_COMPRESSION) / 2.0
+    }
+}
 
-            Pixel::Fire => {
-                if self.ignite_neighbors(col, row, 4) > 0 || num < 1.0 {
-                    self.grid[(u_row, u_col)] = Pixel::Smoke;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-            }
-            Pixel::Oil => {
-                if !(is_less_dense) {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row, u_col - 1 + side)].is_airy() {
-                        self.swap_px((row, col), (row, col + side as i32 - 1));
-                    } else if self.grid[(u_row, u_col - 1 + 2 - side)].is_airy() {
-                        self.swap_px((row, col), (row, col + 1 - side as i32));
-                    }
-                }
-            }
-            Pixel::Smoke => {
-                if num > 98.0 {
-                    self.grid[(u_row, u_col)] = Pixel::Air;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-            }
-            Pixel::Steam => {
-                if num < 0.1 {
-                    self.grid[(u_row, u_col)] = Pixel::Water;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-                if self.grid[(u_row - 1, u_col)].fluid_density().unwrap_or(99) != 3 {
-                    let side = fastrand::choice([0, 2]).unwrap_or(1);
-                    if self.grid[(u_row, u_col - 1 + side)].is_airy() {
-                        self.swap_px((row, col), (row, col + side as i32 - 1));
-                    } else if self.grid[(u_row, u_col - 1 + 2 - side)].is_airy() {
-                        self.swap_px((row, col), (row, col + 1 - side as i32));
-                    }
-                }
-            }
-            Pixel::Glass => {
-                if self.grid[(u_row - 1, u_col)] == Pixel::Fire
-                    || self.grid[(u_row + 1, u_col)] == Pixel::Fire
-                    || self.grid[(u_row, u_col + 1)] == Pixel::Fire
-                    || self.grid[(u_row, u_col - 1)] == Pixel::Fire
-                {
-                    self.grid[(u_row, u_col)] = Pixel::Glass
-                }
-            }
-            Pixel::Grass => {
-                if !self.grid[(u_row - 1, u_col)].is_airy()
-                    || self.grid[(u_row + 1, u_col)].is_airy()
-                {
-                    self.grid[(u_row, u_col)] = Pixel::Dirt;
-                    self.update_texture_px.insert((row as usize, col as usize));
-                }
-            }
-            Pixel::Bedrock => {
-                self.update_texture_px.insert((row as usize, col as usize));
-            }
-            Pixel::Gold | Pixel::Stone | Pixel::Air | Pixel::Wood | Pixel::Loot => {}
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if num > 90.0
-            || (player
-                .view_port_cache
-                .contains(Vec2::new(col as f32, row as f32)))
-        {
-            self.block_percent
-                .insert(this_px, self.block_percent.get(&this_px).unwrap_or(&0) + 1);
-
-            let light_mask_surroundings = [
-                self.light_mask.get_pixel(col as u32 - 1, row as u32 - 1),
-                self.light_mask.get_pixel(col as u32 - 1, row as u32),
-                self.light_mask.get_pixel(col as u32 - 1, row as u32 + 1),
-                self.light_mask.get_pixel(col as u32, row as u32 - 1),
-                self.light_mask.get_pixel(col as u32, row as u32 + 1),
-                self.light_mask.get_pixel(col as u32 + 1, row as u32 - 1),
-                self.light_mask.get_pixel(col as u32 + 1, row as u32),
-                self.light_mask.get_pixel(col as u32 + 1, row as u32 + 1),
-                self.grid[(u_row, u_col)].light_emission(),
-                if self.sky_light[u_col] > u_row {
-                    Color {
-                        r: 1.0,
-                        g: 1.0,
-                        b: 1.0,
-                        a: 0.1,
-                    }
-                } else {
-                    Color {
-                        r: 1.0,
-                        g: 1.0,
-                        b: 1.0,
-                        a: 1.0,
-                    }
-                },
-            ];
+    const SIZE: usize = 32;
 
-            let mut color = self.grid[(u_row, u_col)].light_emission();
+    /// the highest row any of `cols` has water in
+    fn surface(map: &Map, cols: std::ops::Range<usize>) -> usize {
+        (0..SIZE)
+            .find(|row| cols.clone().any(|col| map.grid[(*row, col)] == Pixel::Water))
+            .unwrap_or(SIZE)
+    }
 
-            for c in light_mask_surroundings {
-                if c.a <= color.a {
-                    color = c;
-                }
+    #[test]
+    fn u_bend_levels_out() {
+        // two arms joined along the bottom, with all the water in the left one
+        let mut map = Map::new_seeded(SIZE, "u bend".into(), 3);
+        map.settings.sim_distance = SIZE as i32;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                map.grid[(row, col)] = match (row, col) {
+                    (12..=27, 6..=8) | (25..=27, 6..=24) => Pixel::Water,
+                    (4..=27, 6..=8) | (4..=27, 22..=24) => Pixel::Air,
+                    _ => Pixel::Bedrock,
+                };
             }
-
-            self.light_mask.set_pixel(
-                col as u32,
-                row as u32,
-                Color {
-                    a: (color.a + 0.15 * self.grid[(u_row, u_col)].light_emission().a)
-                        .clamp(0.0, 1.0),
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                },
-            );
         }
+        map.reset_temperature();
+        map.reset_meta();
+        map.reset_fluid();
+        map.reset_stress();
+        let mass: f32 = map.fluid.iter().sum();
+
+        let focus = SimFocus {
+            x: SIZE as f32 / 2.0,
+            y: SIZE as f32 / 2.0,
+            view: (0.0, 0.0, SIZE as f32, SIZE as f32),
+        };
+        for _ in 0..3000 {
+            map.update_state(&focus);
+            map.drain_dirty_px();
+            map.update_light_px.clear();
+        }
+
+        let (left, right) = (surface(&map, 6..9), surface(&map, 22..25));
+        assert!(left.abs_diff(right) <= 1, "left arm at row {left}, right arm at row {right}");
+        assert!((map.fluid.iter().sum::<f32>() - mass).abs() < 0.01, "water was made or lost");
     }
 }
@@ -0,0 +1,162 @@
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+use fastrand::Rng;
+
+use crate::map::{Map, Pixel};
+use crate::season::Season;
+
+/// shortest and longest a spell of weather lasts, in ticks
+const MIN_SPELL: u32 = 3000;
+const MAX_SPELL: u32 = 12000;
+/// chance each column in the simulation gets a drop of rain (or a flake of
+/// snow) per tick
+const RAIN_RATE: f32 = 0.003;
+const SNOW_RATE: f32 = 0.002;
+/// storms rain this many times harder
+const STORM_RAIN: f32 = 2.5;
+/// the prevailing wind is at least this strong in a storm
+const STORM_WIND: f32 = 0.8;
+/// chance of a lightning strike per tick in a storm
+const LIGHTNING_CHANCE: f32 = 0.004;
+/// ticks a lightning bolt stays on screen
+pub const FLASH: u64 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+    Storm,
+}
+
+impl WeatherKind {
+    const ALL: [WeatherKind; 4] = [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Storm];
+
+    pub fn index(self) -> u32 {
+        WeatherKind::ALL.iter().position(|kind| *kind == self).unwrap_or(0) as u32
+    }
+
+    pub fn from_index(index: u32) -> WeatherKind {
+        WeatherKind::ALL.get(index as usize).copied().unwrap_or(WeatherKind::Clear)
+    }
+
+    /// what falls from the sky and the chance of it per column per tick
+    pub fn precipitation(self) -> Option<(Pixel, f32)> {
+        match self {
+            WeatherKind::Clear => None,
+            WeatherKind::Rain => Some((Pixel::Water, RAIN_RATE)),
+            WeatherKind::Snow => Some((Pixel::Snow, SNOW_RATE)),
+            WeatherKind::Storm => Some((Pixel::Water, RAIN_RATE * STORM_RAIN)),
+        }
+    }
+}
+
+impl Display for WeatherKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WeatherKind::Clear => "Clear",
+            WeatherKind::Rain => "Rain",
+            WeatherKind::Snow => "Snow",
+            WeatherKind::Storm => "Storm",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// the weather over the map. it stays the same for a spell of a few
+/// thousand ticks and then picks something new
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// ticks until the weather changes
+    pub remaining: u32,
+    /// the last lightning strike as (tick, row, col), not saved
+    pub strike: Option<(u64, usize, usize)>,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            kind: WeatherKind::Clear,
+            remaining: MIN_SPELL,
+            strike: None,
+        }
+    }
+}
+
+impl Weather {
+    /// counts down the current spell, and picks the next one when it is over.
+    /// it only snows in winter, and in winter it never rains
+    pub fn update(&mut self, rng: &mut Rng, season: Season) {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return;
+        }
+        // clear weather is as likely as everything else put together
+        self.kind = match rng.f32() {
+            x if x < 0.5 => WeatherKind::Clear,
+            x if x < 0.75 => WeatherKind::Rain,
+            x if x < 0.85 => WeatherKind::Snow,
+            _ => WeatherKind::Storm,
+        };
+        self.kind = match (self.kind, season) {
+            (WeatherKind::Rain, Season::Winter) => WeatherKind::Snow,
+            (WeatherKind::Snow, season) if season != Season::Winter => WeatherKind::Rain,
+            (kind, _) => kind,
+        };
+        self.remaining = rng.u32(MIN_SPELL..=MAX_SPELL);
+    }
+
+    /// whether a lightning bolt should be drawn this tick
+    pub fn flash(&self, tick: u64) -> Option<(usize, usize)> {
+        self.strike
+            .filter(|(at, _, _)| tick < at + FLASH)
+            .map(|(_, row, col)| (row, col))
+    }
+}
+
+impl Map {
+    /// moves the weather along and lets whatever it brings fall on the part
+    /// of the map being simulated. drops start at the top of the simulation
+    /// so they can fall, but only in columns open to the sky
+    pub fn update_weather(&mut self, rows: RangeInclusive<usize>, cols: RangeInclusive<usize>) {
+        self.weather.update(&mut self.rng.sim, self.clock.season());
+        let kind = self.weather.kind;
+
+        if kind == WeatherKind::Storm {
+            let side = if self.wind.target < 0.0 { -1.0 } else { 1.0 };
+            self.wind.target = side * self.wind.target.abs().max(STORM_WIND);
+        }
+
+        if let Some((pixel, rate)) = kind.precipitation() {
+            let top = *rows.start();
+            for col in cols.clone() {
+                if self.rng.sim.f32() >= rate {
+                    continue;
+                }
+                if (2..=top).all(|row| self.grid[(row, col)].is_airy()) && self.grid[(top, col)] == Pixel::Air {
+                    self.set_px(top, col, pixel);
+                }
+            }
+        }
+
+        if kind == WeatherKind::Storm && self.rng.sim.f32() < LIGHTNING_CHANCE {
+            let col = self.rng.sim.usize(cols);
+            self.strike_lightning(col);
+        }
+    }
+
+    /// hits the first thing in a column that isn't air, setting it alight if
+    /// it burns and leaving a flash of fire in the air above it
+    pub fn strike_lightning(&mut self, col: usize) {
+        let size = self.size as usize;
+        let Some(row) = (2..size - 2).find(|row| !self.grid[(*row, col)].is_airy()) else {
+            return;
+        };
+        self.weather.strike = Some((self.tick, row, col));
+        self.ignite_px(col as i32, row as i32, true);
+        if self.grid[(row - 1, col)] == Pixel::Air {
+            self.set_px(row - 1, col, Pixel::Fire);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use fastrand::Rng;
+use grid::Grid;
+
+use crate::map::Pixel;
+
+/// the wind is worked out for square blocks this many pixels wide
+pub const WIND_BLOCK: usize = 16;
+/// updates between working out again where the terrain blocks the wind
+const WIND_REFRESH: u64 = 20;
+/// updates between the prevailing wind picking a new strength to head for
+const WIND_CHANGE: u64 = 600;
+/// fraction of the way to its new strength the prevailing wind moves per update
+const WIND_EASE: f32 = 0.002;
+/// strength the wind gets back per block of open air after something
+/// blocked it
+const WIND_RECOVERY: f32 = 0.1;
+/// rows of each block checked to see how open it is
+const SAMPLES: usize = 4;
+
+/// wind blowing across the map. there is one prevailing wind that slowly
+/// changes strength and direction, broken up into gusts and blocked by
+/// terrain
+pub struct Wind {
+    /// the prevailing wind, -1.0 (hard to the left) - 1.0 (hard to the right)
+    pub strength: f32,
+    /// the strength the prevailing wind is heading for
+    pub target: f32,
+    /// wind in each block, after gusts and shelter
+    field: Grid<(f32, f32)>,
+}
+
+impl Wind {
+    pub fn new(size: usize) -> Wind {
+        let blocks = size.div_ceil(WIND_BLOCK);
+        Wind {
+            strength: 0.0,
+            target: 0.0,
+            field: Grid::from_vec(vec![(0.0, 0.0); blocks.pow(2)], blocks),
+        }
+    }
+
+    /// the wind at a pixel as (x, y), about how many pixels per update it
+    /// pushes the lightest things. up is negative, like rows
+    pub fn at(&self, row: usize, col: usize) -> (f32, f32) {
+        self.field
+            .get(row / WIND_BLOCK, col / WIND_BLOCK)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// moves the prevailing wind along, and every so often works out the
+    /// wind in each block again. returns whether it did
+    pub fn update(&mut self, grid: &Grid<Pixel>, tick: u64, rng: &mut Rng) -> bool {
+        if tick % WIND_CHANGE == 0 {
+            self.target = rng.f32() * 2.0 - 1.0;
+        }
+        self.strength += (self.target - self.strength) * WIND_EASE;
+
+        if tick % WIND_REFRESH != 0 {
+            return false;
+        }
+        self.refresh(grid, tick);
+        true
+    }
+
+    /// works out the wind in every block. a few rows of each block are
+    /// walked the way the wind blows: anything solid across a row stops it
+    /// and it only builds back up over open air. where it is stopped it gets
+    /// pushed up over whatever is in the way
+    pub fn refresh(&mut self, grid: &Grid<Pixel>, tick: u64) {
+        let blocks = self.field.rows();
+        let time = tick as f32 / WIND_CHANGE as f32;
+
+        for block_row in 0..blocks {
+            let mut exposure = [1.0; SAMPLES];
+            for i in 0..blocks {
+                let block_col = if self.strength >= 0.0 { i } else { blocks - 1 - i };
+                let before: f32 = exposure.iter().sum::<f32>() / SAMPLES as f32;
+                for (sample, exposure) in exposure.iter_mut().enumerate() {
+                    *exposure = if is_open(grid, block_row, block_col, sample) {
+                        (*exposure + WIND_RECOVERY).min(1.0)
+                    } else {
+                        0.0
+                    };
+                }
+                let after: f32 = exposure.iter().sum::<f32>() / SAMPLES as f32;
+
+                // gusts roll across the map and come and go
+                let gust = 0.75 + 0.25 * (block_col as f32 * 0.9 + block_row as f32 * 0.4 - time * 6.0).sin();
+                let x = self.strength * gust * after;
+                let y = -(before - after).max(0.0) * self.strength.abs() * gust;
+                self.field[(block_row, block_col)] = (x, y);
+            }
+        }
+    }
+}
+
+/// whether one of the checked rows of a block is open all the way across,
+/// so even a thin wall stops the wind
+fn is_open(grid: &Grid<Pixel>, block_row: usize, block_col: usize, sample: usize) -> bool {
+    let step = WIND_BLOCK / SAMPLES;
+    let row = block_row * WIND_BLOCK + sample * step + step / 2;
+    let cols = block_col * WIND_BLOCK..((block_col + 1) * WIND_BLOCK).min(grid.cols());
+    row < grid.rows() && cols.into_iter().all(|col| grid[(row, col)].is_airy())
+}